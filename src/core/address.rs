@@ -2,13 +2,95 @@ use std::fmt::{self, Display};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+use crate::core::{
+    base58,
+    consensus::Network,
+    error::{LuxError, LuxResult},
+    keys::PublicKey,
+};
+
+// Length of the RIPEMD160(SHA256(key)) payload, see `crypto::hash160`
+pub const PUBKEY_HASH_LENGTH: usize = 20;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Address(String);
 
 impl Address {
+    // Wraps the string as is, without any validation. Prefer
+    // `Address::from_base58check` for user supplied addresses.
     pub fn new(address: String) -> Self {
         Self(address)
     }
+
+    // The network's version byte comes first, so addresses of different
+    // networks never decode as one another
+    pub fn from_pubkey_hash(hash: &[u8; PUBKEY_HASH_LENGTH], network: Network) -> Self {
+        Self(base58::base58check_encode(network.address_version(), hash))
+    }
+
+    // The address only the holder of `pubkey`'s secret key can spend from
+    pub fn from_pubkey(pubkey: &PublicKey, network: Network) -> Self {
+        Self::from_pubkey_hash(&pubkey.hash160(), network)
+    }
+
+    // Decodes the address and verifies its version byte, length and the
+    // trailing 4 byte double SHA-256 checksum. The address may belong to any
+    // network, see `Address::network`.
+    pub fn from_base58check(s: &str) -> LuxResult<Self> {
+        decode(s)?;
+        Ok(Self(s.to_string()))
+    }
+
+    // Same as `from_base58check`, also rejecting addresses of other networks
+    pub fn from_base58check_for(s: &str, expected: Network) -> LuxResult<Self> {
+        let (network, _) = decode(s)?;
+        if network != expected {
+            return Err(LuxError::InvalidAddress(format!(
+                "{}: {:?} address used on {:?}",
+                s, network, expected
+            )));
+        }
+
+        Ok(Self(s.to_string()))
+    }
+
+    // Network the address was encoded for, None if it isn't a valid
+    // base58check address, e.g. one wrapped by `Address::new`
+    pub fn network(&self) -> Option<Network> {
+        decode(&self.0).ok().map(|(network, _)| network)
+    }
+
+    // Hash of the public key allowed to spend outputs paying the address, None
+    // if it isn't a valid base58check address
+    pub fn pubkey_hash(&self) -> Option<[u8; PUBKEY_HASH_LENGTH]> {
+        decode(&self.0).ok().map(|(_, hash)| hash)
+    }
+
+    pub fn to_base58check(&self) -> String {
+        self.0.clone()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+fn decode(s: &str) -> LuxResult<(Network, [u8; PUBKEY_HASH_LENGTH])> {
+    let (version, payload) = base58::base58check_decode(s)
+        .map_err(|e| LuxError::InvalidAddress(format!("{}: {}", s, e)))?;
+    let hash = <[u8; PUBKEY_HASH_LENGTH]>::try_from(payload.as_slice()).map_err(|_| {
+        LuxError::InvalidAddress(format!(
+            "{}: expected a {} byte key hash but got {} bytes",
+            s,
+            PUBKEY_HASH_LENGTH,
+            payload.len()
+        ))
+    })?;
+
+    let network = Network::from_address_version(version).ok_or_else(|| {
+        LuxError::InvalidAddress(format!("{}: unknown version byte {:#04x}", s, version))
+    })?;
+    Ok((network, hash))
 }
 
 impl Display for Address {
@@ -16,3 +98,79 @@ impl Display for Address {
         write!(f, "{}", self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Address;
+    use crate::core::{base58, consensus::Network, crypto, error::LuxError, keys::KeyPair};
+
+    #[test]
+    fn base58check_round_trip() {
+        let address = Address::from_pubkey_hash(&crypto::hash160(b"alice"), Network::Mainnet);
+        let encoded = address.to_base58check();
+
+        let decoded = Address::from_base58check(&encoded).unwrap();
+        assert_eq!(decoded.to_base58check(), encoded);
+    }
+
+    #[test]
+    fn network_is_encoded() {
+        let hash = crypto::hash160(b"alice");
+        let mainnet = Address::from_pubkey_hash(&hash, Network::Mainnet);
+        let testnet = Address::from_pubkey_hash(&hash, Network::Testnet);
+
+        assert_ne!(mainnet, testnet);
+        assert_eq!(mainnet.network(), Some(Network::Mainnet));
+        assert_eq!(testnet.network(), Some(Network::Testnet));
+        assert_eq!(Address::new("alice".to_string()).network(), None);
+
+        assert!(Address::from_base58check_for(testnet.as_str(), Network::Testnet).is_ok());
+        assert!(matches!(
+            Address::from_base58check_for(testnet.as_str(), Network::Mainnet),
+            Err(LuxError::InvalidAddress(_))
+        ));
+    }
+
+    #[test]
+    fn pays_to_the_key_hash() {
+        let pubkey = KeyPair::generate().public_key();
+        let address = Address::from_pubkey(&pubkey, Network::Testnet);
+        assert_eq!(address.pubkey_hash(), Some(pubkey.hash160()));
+        assert_eq!(Address::new("alice".to_string()).pubkey_hash(), None);
+
+        // A full SHA-256 is not a key hash
+        let long = base58::base58check_encode(
+            Network::Mainnet.address_version(),
+            crypto::hash(b"alice").as_slice(),
+        );
+        assert!(matches!(
+            Address::from_base58check(&long),
+            Err(LuxError::InvalidAddress(_))
+        ));
+    }
+
+    #[test]
+    fn base58check_rejects_typos() {
+        let encoded = Address::from_pubkey_hash(&crypto::hash160(b"alice"), Network::Mainnet)
+            .to_base58check();
+
+        // Swap two adjacent characters, keeping the length the same
+        let mut typo: Vec<char> = encoded.chars().collect();
+        typo.swap(5, 6);
+        let typo: String = typo.into_iter().collect();
+
+        assert_ne!(typo, encoded);
+        assert!(matches!(
+            Address::from_base58check(&typo),
+            Err(LuxError::InvalidAddress(_))
+        ));
+    }
+
+    #[test]
+    fn base58check_rejects_malformed_input() {
+        assert!(Address::from_base58check("").is_err());
+        assert!(Address::from_base58check("alice").is_err());
+        // '0' is not part of the base58 alphabet
+        assert!(Address::from_base58check("0OIl").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::core::{
+    crypto,
+    error::{LuxError, LuxResult},
+};
+
+// Leading bytes of the double SHA-256 appended to the version and payload
+const CHECKSUM_LENGTH: usize = 4;
+
+// Base58 of the version byte, the payload and the checksum of both, so that a
+// mistyped character is caught on decoding instead of changing the payload
+pub fn base58check_encode(version: u8, payload: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(1 + payload.len() + CHECKSUM_LENGTH);
+    bytes.push(version);
+    bytes.extend_from_slice(payload);
+    bytes.extend_from_slice(&checksum(&bytes));
+
+    bs58::encode(bytes).into_string()
+}
+
+// Splits a base58check string back into its version byte and payload. A valid
+// encoding with the wrong checksum fails with `LuxError::ChecksumMismatch`,
+// anything else malformed with `LuxError::InvalidBase58`.
+pub fn base58check_decode(s: &str) -> LuxResult<(u8, Vec<u8>)> {
+    let mut bytes = bs58::decode(s)
+        .into_vec()
+        .map_err(|e| LuxError::InvalidBase58(e.to_string()))?;
+    if bytes.len() < 1 + CHECKSUM_LENGTH {
+        return Err(LuxError::InvalidBase58(format!(
+            "Expected at least {} bytes but got {}.",
+            1 + CHECKSUM_LENGTH,
+            bytes.len()
+        )));
+    }
+
+    let actual = bytes.split_off(bytes.len() - CHECKSUM_LENGTH);
+    let expected = checksum(&bytes);
+    if actual != expected {
+        return Err(LuxError::ChecksumMismatch {
+            expected: crypto::as_hex(&expected),
+            actual: crypto::as_hex(&actual),
+        });
+    }
+
+    let payload = bytes.split_off(1);
+    Ok((bytes[0], payload))
+}
+
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_LENGTH] {
+    let mut checksum = [0; CHECKSUM_LENGTH];
+    checksum.copy_from_slice(&crypto::hash256(bytes).as_slice()[..CHECKSUM_LENGTH]);
+    checksum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base58check_decode, base58check_encode};
+    use crate::core::error::LuxError;
+
+    #[test]
+    fn matches_known_address() {
+        // Version 0 pay to public key hash address from the Bitcoin wiki
+        let payload = hex::decode("010966776006953d5567439e5e39f86a0d273bee").unwrap();
+        let encoded = base58check_encode(0, &payload);
+        assert_eq!(encoded, "16UwLL9Risc3QfPqBUvKofHmBQ7wMtjvM");
+        assert_eq!(base58check_decode(&encoded).unwrap(), (0, payload));
+    }
+
+    #[test]
+    fn round_trips_any_payload() {
+        for payload in [vec![], vec![0; 3], (0..=255).collect()] {
+            let encoded = base58check_encode(0x6f, &payload);
+            assert_eq!(base58check_decode(&encoded).unwrap(), (0x6f, payload));
+        }
+    }
+
+    #[test]
+    fn checksum_mismatch_is_distinct() {
+        let mut typo = base58check_encode(0, b"alice").into_bytes();
+        let last = typo.len() - 1;
+        typo[last] = if typo[last] == b'2' { b'3' } else { b'2' };
+        let typo = String::from_utf8(typo).unwrap();
+
+        assert!(matches!(
+            base58check_decode(&typo),
+            Err(LuxError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        // '0' is not part of the base58 alphabet
+        assert!(matches!(
+            base58check_decode("0OIl"),
+            Err(LuxError::InvalidBase58(_))
+        ));
+        assert!(matches!(
+            base58check_decode(""),
+            Err(LuxError::InvalidBase58(_))
+        ));
+        assert!(matches!(
+            base58check_decode("1111"),
+            Err(LuxError::InvalidBase58(_))
+        ));
+    }
+}
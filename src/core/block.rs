@@ -1,12 +1,35 @@
-use std::fmt::{self, Display};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use serde::{Deserialize, Serialize};
 
 use crate::core::{
-    crypto::{self, MerkleHash, Sha256},
-    transaction::Transaction,
+    address::Address,
+    consensus::ConsensusParams,
+    crypto::{self, DoubleSha256Algorithm, HashAlgorithm, MerkleHash, MerkleTree, Sha256},
+    difficulty,
+    error::{LuxError, LuxResult},
+    luxcoin::Luxcoin,
+    timestamp::Timestamp,
+    transaction::{
+        OutputIndex, Transaction, TransactionId, TransactionInput, TransactionOutput,
+        WITNESS_SCALE_FACTOR,
+    },
 };
 
+// Length of a header's canonical encoding
+pub const HEADER_SIZE: usize = 80;
+
+// Header version produced by this node, and the only one it accepts
+pub const BLOCK_VERSION: u32 = 1;
+
+// Fixed so that every node derives the same genesis block
+pub const GENESIS_TIMESTAMP: Timestamp = Timestamp::new(1_636_156_800);
+
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct BlockHash(Sha256);
 
@@ -15,9 +38,21 @@ impl BlockHash {
         Self(hash)
     }
 
+    // All bits set to 0, used as the previous hash of the genesis block
+    pub const fn zero() -> Self {
+        Self(Sha256::new([0; 32]))
+    }
+
     pub fn as_slice(&self) -> &[u8] {
         &self.0.as_ref()[..]
     }
+
+    // Proof-of-work check: the hash, read as a 256-bit big-endian integer, must
+    // not exceed the target. The derived `Ord` on the byte array compares
+    // lexicographically, which is exactly big-endian numeric ordering.
+    pub fn meets_target(&self, target: &BlockHash) -> bool {
+        self <= target
+    }
 }
 
 impl AsRef<Sha256> for BlockHash {
@@ -26,6 +61,14 @@ impl AsRef<Sha256> for BlockHash {
     }
 }
 
+impl FromStr for BlockHash {
+    type Err = LuxError;
+
+    fn from_str(s: &str) -> LuxResult<Self> {
+        s.parse().map(Self)
+    }
+}
+
 impl Display for BlockHash {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", crypto::as_hex(self.as_slice()))
@@ -36,16 +79,42 @@ impl Display for BlockHash {
 pub struct BlockHeader {
     previous_block_hash: BlockHash,
     merkle_root: MerkleHash,
-    timestamp: u32,
+    timestamp: Timestamp,
     difficulty: u32,
     nonce: u32,
+    // Serialized last and defaulted so headers written before versioning
+    // still deserialize
+    #[serde(default = "default_block_version")]
+    version: u32,
+}
+
+fn default_block_version() -> u32 {
+    BLOCK_VERSION
 }
 
 impl BlockHeader {
     pub fn new(
         previous_block_hash: BlockHash,
         merkle_root: MerkleHash,
-        timestamp: u32,
+        timestamp: Timestamp,
+        difficulty: u32,
+        nonce: u32,
+    ) -> Self {
+        Self::with_version(
+            BLOCK_VERSION,
+            previous_block_hash,
+            merkle_root,
+            timestamp,
+            difficulty,
+            nonce,
+        )
+    }
+
+    pub fn with_version(
+        version: u32,
+        previous_block_hash: BlockHash,
+        merkle_root: MerkleHash,
+        timestamp: Timestamp,
         difficulty: u32,
         nonce: u32,
     ) -> Self {
@@ -55,17 +124,123 @@ impl BlockHeader {
             timestamp,
             difficulty,
             nonce,
+            version,
         }
     }
 
+    // Canonical fixed size encoding: version as a little endian u32, previous
+    // block hash, Merkle root, then timestamp, difficulty and nonce as little
+    // endian u32s. Used for hashing and for sending headers on their own.
+    pub fn serialize_compact(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0; HEADER_SIZE];
+        bytes[..4].copy_from_slice(&self.version.to_le_bytes());
+        bytes[4..36].copy_from_slice(self.previous_block_hash.as_slice());
+        bytes[36..68].copy_from_slice(self.merkle_root.as_slice());
+        bytes[68..72].copy_from_slice(&self.timestamp.as_secs().to_le_bytes());
+        bytes[72..76].copy_from_slice(&self.difficulty.to_le_bytes());
+        bytes[76..].copy_from_slice(&self.nonce.to_le_bytes());
+        bytes
+    }
+
+    // Inverse of `serialize_compact`. Fails unless given exactly
+    // `HEADER_SIZE` bytes; the header's contents aren't validated.
+    pub fn deserialize_compact(bytes: &[u8]) -> LuxResult<Self> {
+        let bytes: &[u8; HEADER_SIZE] = bytes.try_into().map_err(|_| {
+            LuxError::InvalidBlock(format!(
+                "Header must be {} bytes but got: {}.",
+                HEADER_SIZE,
+                bytes.len()
+            ))
+        })?;
+        let u32_at =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+
+        Ok(Self::with_version(
+            u32_at(0),
+            BlockHash::new(Sha256::try_from(&bytes[4..36])?),
+            MerkleHash::new(Sha256::try_from(&bytes[36..68])?),
+            Timestamp::new(u32_at(68)),
+            u32_at(72),
+            u32_at(76),
+        ))
+    }
+
+    // Checks that every header builds on the one before it and meets the target
+    // of its own difficulty, which is all headers-first sync can verify before
+    // the blocks' transactions arrive
+    pub fn validate_chain(headers: &[BlockHeader], params: &ConsensusParams) -> LuxResult<()> {
+        for (i, header) in headers.iter().enumerate() {
+            if i > 0 && header.previous_block_hash != headers[i - 1].hash() {
+                return Err(LuxError::InvalidBlock(format!(
+                    "Header: {} doesn't build on: {}.",
+                    header.hash(),
+                    headers[i - 1].hash()
+                )));
+            }
+
+            let target = header.target()?;
+            let pow_hash = header.pow_hash(params.pow_algorithm);
+            if !pow_hash.meets_target(&target) {
+                return Err(LuxError::ProofOfWorkFailed {
+                    hash: pow_hash,
+                    target,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Block ids are the double SHA-256 (`crypto::hash256`) of the header's
+    // canonical encoding, see `BlockHeader::serialize_compact`
     pub fn hash(&self) -> BlockHash {
-        let data = format!(
-            "{}{}{}{}{}",
-            self.previous_block_hash, self.merkle_root, self.timestamp, self.difficulty, self.nonce
-        );
+        BlockHash::new(crypto::hash256(&self.serialize_compact()))
+    }
+
+    // Hash checked against the target, see `ConsensusParams::pow_algorithm`
+    pub fn pow_hash(&self, algorithm: &dyn HashAlgorithm) -> BlockHash {
+        BlockHash::new(algorithm.hash(&self.serialize_compact()))
+    }
 
-        let hash = crypto::hash(data.as_bytes());
-        BlockHash::new(hash)
+    // Searches for a nonce whose header hash meets the target, leaving the
+    // winning nonce in the header. When the nonce space wraps, the timestamp is
+    // bumped by one second and the search starts over. Setting `cancel` from
+    // another thread stops the search.
+    pub fn mine(&mut self, target: &BlockHash, cancel: &AtomicBool) -> LuxResult<u32> {
+        self.mine_with(&DoubleSha256Algorithm, target, cancel)
+    }
+
+    // Same as `mine`, for networks using another proof of work hash
+    pub fn mine_with(
+        &mut self,
+        algorithm: &dyn HashAlgorithm,
+        target: &BlockHash,
+        cancel: &AtomicBool,
+    ) -> LuxResult<u32> {
+        loop {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(LuxError::MiningCancelled);
+            }
+
+            if self.pow_hash(algorithm).meets_target(target) {
+                return Ok(self.nonce);
+            }
+
+            self.nonce = match self.nonce.checked_add(1) {
+                Some(nonce) => nonce,
+                None => {
+                    self.timestamp = self
+                        .timestamp
+                        .checked_add_secs(1)
+                        .ok_or(LuxError::MiningExhausted)?;
+                    0
+                }
+            };
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
     }
 
     pub fn previous_block_hash(&self) -> &BlockHash {
@@ -76,7 +251,7 @@ impl BlockHeader {
         &self.merkle_root
     }
 
-    pub fn timestamp(&self) -> u32 {
+    pub fn timestamp(&self) -> Timestamp {
         self.timestamp
     }
 
@@ -84,11 +259,29 @@ impl BlockHeader {
         self.difficulty
     }
 
+    // Largest proof of work hash meeting the header's difficulty
+    pub fn target(&self) -> LuxResult<BlockHash> {
+        difficulty::difficulty_to_target(self.difficulty)
+    }
+
     pub fn nonce(&self) -> u32 {
         self.nonce
     }
 }
 
+impl Display for BlockHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "version:        {}", self.version)?;
+        writeln!(f, "previous block: {}", self.previous_block_hash)?;
+        writeln!(f, "merkle root:    {}", self.merkle_root)?;
+        writeln!(f, "timestamp:      {}", self.timestamp)?;
+        writeln!(f, "difficulty:     {}", self.difficulty)?;
+        write!(f, "nonce:          {}", self.nonce)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "BlockData")]
 pub struct Block {
     id: BlockHash,
     header: BlockHeader,
@@ -105,6 +298,47 @@ impl Block {
         }
     }
 
+    // Builds the first block of a chain: a single coinbase transaction paying
+    // `subsidy` to `coinbase_to`, on top of the all-zero block hash. The
+    // header is mined with the network's proof of work hash starting from a
+    // zero nonce, so the same inputs always produce the same block.
+    pub fn genesis(
+        coinbase_to: Address,
+        subsidy: Luxcoin,
+        difficulty: u32,
+        params: &ConsensusParams,
+    ) -> LuxResult<Self> {
+        let coinbase = Transaction::new_coinbase(coinbase_to, subsidy, Luxcoin::ZERO, 0)?;
+        let transactions = vec![coinbase];
+
+        let merkle_root = MerkleTree::from(&transactions).root().clone();
+        let mut header = BlockHeader::new(
+            BlockHash::zero(),
+            merkle_root,
+            GENESIS_TIMESTAMP,
+            difficulty,
+            0,
+        );
+        header.mine_with(
+            params.pow_algorithm,
+            &crypto::target_hash(difficulty)?,
+            &AtomicBool::new(false),
+        )?;
+
+        Ok(Self::new(header, transactions))
+    }
+
+    // The id of the genesis block built from the same parameters, for clients
+    // pinning the chain they expect to join
+    pub fn genesis_hash(
+        coinbase_to: Address,
+        subsidy: Luxcoin,
+        difficulty: u32,
+        params: &ConsensusParams,
+    ) -> LuxResult<BlockHash> {
+        Self::genesis(coinbase_to, subsidy, difficulty, params).map(|block| block.id)
+    }
+
     pub fn id(&self) -> &BlockHash {
         &self.id
     }
@@ -116,4 +350,849 @@ impl Block {
     pub fn transactions(&self) -> &Vec<Transaction> {
         &self.transactions
     }
+
+    // Every output created by the block, paired with the outpoint that spends
+    // it, in transaction order
+    pub fn iter_outpoints(
+        &self,
+    ) -> impl Iterator<Item = (TransactionId, OutputIndex, &TransactionOutput)> {
+        self.transactions.iter().flat_map(|transaction| {
+            transaction
+                .outputs()
+                .iter()
+                .enumerate()
+                .map(move |(i, output)| (*transaction.id(), OutputIndex::new(i as i32), output))
+        })
+    }
+
+    // Merkle root of the block's transaction ids. A block without transactions
+    // gets `MerkleTree::empty_root`.
+    pub fn compute_merkle_root(&self) -> MerkleHash {
+        MerkleTree::from(&self.transactions).root().clone()
+    }
+
+    // Whether the header commits to the block's transactions
+    pub fn merkle_root_matches(&self) -> bool {
+        &self.compute_merkle_root() == self.header.merkle_root()
+    }
+
+    // Height embedded in the coinbase input, if the block has one. Only blocks
+    // a `Blockchain` connected are known to sit at that height.
+    pub fn height(&self) -> Option<u32> {
+        self.transactions
+            .first()
+            .and_then(|transaction| transaction.inputs().first())
+            .and_then(TransactionInput::coinbase_height)
+    }
+
+    // Sum of every output in the block, coinbase included
+    pub fn total_output(&self) -> Luxcoin {
+        self.transactions
+            .iter()
+            .flat_map(|transaction| transaction.outputs())
+            .map(TransactionOutput::amount)
+            .sum()
+    }
+
+    // Length of the binary (bincode) encoding, computed without serializing
+    // into a buffer
+    pub fn serialized_size(&self) -> usize {
+        // Every field has an infallible Serialize impl
+        bincode::serialized_size(self).expect("blocks always serialize") as usize
+    }
+
+    // The weight of the header and transaction count, which carry no
+    // signature data, plus the weight of every transaction
+    pub fn weight(&self) -> u64 {
+        // Encoded like the block itself: the id, the header, then the
+        // transaction count as a u64
+        let overhead = bincode::serialized_size(&(&self.id, &self.header, 0u64))
+            .expect("headers always serialize");
+        overhead * WITNESS_SCALE_FACTOR
+            + self
+                .transactions
+                .iter()
+                .map(Transaction::weight)
+                .sum::<u64>()
+    }
+
+    // Rejects a block listing the same transaction twice, or two transactions
+    // spending the same output. Either would otherwise only surface while the
+    // block is applied to the UTXO set.
+    pub fn validate_unique_spends(&self) -> LuxResult<()> {
+        let mut ids = HashSet::new();
+        let mut outpoints = HashSet::new();
+        for transaction in &self.transactions {
+            if !ids.insert(transaction.id()) {
+                return Err(LuxError::InvalidBlock(format!(
+                    "Block: {} contains transaction: {} more than once.",
+                    self.id,
+                    transaction.id()
+                )));
+            }
+
+            for input in transaction.inputs().iter().filter(|i| !i.is_coinbase()) {
+                if !outpoints.insert((input.utxo_id(), input.output_index())) {
+                    return Err(LuxError::DoubleSpend {
+                        utxo: *input.utxo_id(),
+                        index: *input.output_index(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_size(&self, max_size: usize) -> LuxResult<()> {
+        let size = self.serialized_size();
+        if size > max_size {
+            return Err(LuxError::InvalidBlock(format!(
+                "Block: {} is {} bytes, exceeding the maximum of {}.",
+                self.id, size, max_size
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_weight(&self, max_weight: u64) -> LuxResult<()> {
+        let weight = self.weight();
+        if weight > max_weight {
+            return Err(LuxError::InvalidBlock(format!(
+                "Block: {} weighs {}, exceeding the maximum of {}.",
+                self.id, weight, max_weight
+            )));
+        }
+
+        Ok(())
+    }
+
+    // Context free checks a node runs before accepting a block: the proof of
+    // work against the header's difficulty, a single coinbase in first
+    // position, unique transactions and spends, the Merkle root and the size
+    // and weight limits
+    pub fn validate(&self, params: &ConsensusParams) -> LuxResult<()> {
+        if self.header.version != BLOCK_VERSION {
+            return Err(LuxError::InvalidBlock(format!(
+                "Block: {} has unsupported version: {}.",
+                self.id, self.header.version
+            )));
+        }
+
+        let target = self.header.target()?;
+        let pow_hash = self.header.pow_hash(params.pow_algorithm);
+        if !pow_hash.meets_target(&target) {
+            return Err(LuxError::ProofOfWorkFailed {
+                hash: pow_hash,
+                target,
+            });
+        }
+
+        match self.transactions.first() {
+            None => {
+                return Err(LuxError::InvalidBlock(format!(
+                    "Block: {} has no transactions.",
+                    self.id
+                )))
+            }
+            Some(transaction) if !transaction.is_coinbase() => {
+                return Err(LuxError::InvalidCoinbase(format!(
+                    "Block: {} doesn't start with a coinbase transaction.",
+                    self.id
+                )))
+            }
+            _ => {}
+        }
+
+        let extra_coinbase = self.transactions[1..].iter().any(|transaction| {
+            transaction
+                .inputs()
+                .iter()
+                .any(TransactionInput::is_coinbase)
+        });
+        if extra_coinbase {
+            return Err(LuxError::InvalidCoinbase(format!(
+                "Block: {} has more than one coinbase transaction.",
+                self.id
+            )));
+        }
+        self.validate_unique_spends()?;
+
+        if !self.merkle_root_matches() {
+            return Err(LuxError::MerkleRootMismatch {
+                expected: self.header.merkle_root().clone(),
+                actual: self.compute_merkle_root(),
+            });
+        }
+
+        self.validate_size(params.max_block_size)?;
+        self.validate_weight(params.max_block_weight)
+    }
+
+    // Pretty printed JSON for explorers and debugging: the block id, `height`
+    // when given, the header and the transactions along with their ids.
+    // Hashes appear as hex strings.
+    pub fn to_json(&self, height: Option<u32>) -> String {
+        let json = BlockJson {
+            id: &self.id,
+            height,
+            header: &self.header,
+            transactions: &self.transactions,
+        };
+        // Every field has an infallible Serialize impl
+        serde_json::to_string_pretty(&json).expect("blocks always serialize")
+    }
+
+    // Parses the output of `Block::to_json`. The id must match the header,
+    // a height must match the coinbase, and the block must pass
+    // `Block::validate` under `params`.
+    pub fn from_json(json: &str, params: &ConsensusParams) -> LuxResult<Self> {
+        let data: BlockJsonData =
+            serde_json::from_str(json).map_err(|e| LuxError::InvalidBlock(e.to_string()))?;
+        let block = Self::new(data.header, data.transactions);
+        if block.id != data.id {
+            return Err(LuxError::InvalidBlock(format!(
+                "Block id: {} doesn't match its header hash: {}.",
+                data.id, block.id
+            )));
+        }
+        if data.height.is_some() && data.height != block.height() {
+            return Err(LuxError::InvalidBlock(format!(
+                "Block: {} is listed at height: {:?} but its coinbase says: {:?}.",
+                block.id,
+                data.height,
+                block.height()
+            )));
+        }
+
+        block.validate(params)?;
+        Ok(block)
+    }
+}
+
+// One line summary for logs, e.g. when a block is received or mined
+impl Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block {}", self.id)?;
+        if let Some(height) = self.height() {
+            write!(f, " at height {}", height)?;
+        }
+        write!(
+            f,
+            ", previous: {}, time: {}, transactions: {}, output: {}",
+            self.header.previous_block_hash,
+            self.header.timestamp,
+            self.transactions.len(),
+            self.total_output()
+        )
+    }
+}
+
+// Wire form of a `Block`. The serialized id is not trusted, it is recomputed
+// from the header.
+#[derive(Deserialize)]
+struct BlockData {
+    id: BlockHash,
+    header: BlockHeader,
+    transactions: Vec<Transaction>,
+}
+
+impl From<BlockData> for Block {
+    fn from(data: BlockData) -> Self {
+        Self::new(data.header, data.transactions)
+    }
+}
+
+// JSON form of a `Block`, see `Block::to_json`
+#[derive(Serialize)]
+struct BlockJson<'a> {
+    id: &'a BlockHash,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<u32>,
+    header: &'a BlockHeader,
+    transactions: &'a [Transaction],
+}
+
+#[derive(Deserialize)]
+struct BlockJsonData {
+    id: BlockHash,
+    #[serde(default)]
+    height: Option<u32>,
+    header: BlockHeader,
+    transactions: Vec<Transaction>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use super::{Block, BlockHash, BlockHeader, BLOCK_VERSION};
+    use crate::core::{
+        address::Address,
+        consensus::ConsensusParams,
+        crypto::{self, target_hash, MerkleHash, MerkleTree, Sha256, Sha256Algorithm},
+        error::LuxError,
+        fixtures,
+        luxcoin::Luxcoin,
+        timestamp::Timestamp,
+        transaction::{
+            OutputIndex, Transaction, TransactionInput, TransactionOutput, WITNESS_SCALE_FACTOR,
+        },
+    };
+
+    fn block_hash(bytes: [u8; 32]) -> BlockHash {
+        BlockHash::new(Sha256::new(bytes))
+    }
+
+    fn coinbase(amount: i64) -> Transaction {
+        Transaction::new(
+            vec![TransactionInput::new_coinbase(0)],
+            vec![
+                TransactionOutput::new(Address::new("miner".to_string()), Luxcoin::new(amount))
+                    .unwrap(),
+            ],
+            0,
+        )
+        .unwrap()
+    }
+
+    fn payment() -> Transaction {
+        Transaction::new(
+            vec![TransactionInput::new(
+                *coinbase(50).id(),
+                OutputIndex::new(0),
+            )],
+            vec![
+                TransactionOutput::new(Address::new("alice".to_string()), Luxcoin::new(50))
+                    .unwrap(),
+            ],
+            0,
+        )
+        .unwrap()
+    }
+
+    fn mined_block(transactions: Vec<Transaction>, difficulty: u32) -> Block {
+        fixtures::mine_block(
+            &BlockHash::zero(),
+            transactions,
+            difficulty,
+            Timestamp::new(0),
+        )
+    }
+
+    fn header() -> BlockHeader {
+        BlockHeader::new(
+            block_hash([0; 32]),
+            MerkleHash::new(crypto::hash(b"transactions")),
+            Timestamp::new(1_600_000_000),
+            8,
+            0,
+        )
+    }
+
+    #[test]
+    fn compact_round_trip() {
+        let header = header();
+        let bytes = header.serialize_compact();
+        let decoded = BlockHeader::deserialize_compact(&bytes).unwrap();
+
+        assert_eq!(decoded.serialize_compact(), bytes);
+        assert_eq!(decoded.hash(), header.hash());
+        assert!(matches!(
+            BlockHeader::deserialize_compact(&bytes[1..]),
+            Err(LuxError::InvalidBlock(_))
+        ));
+    }
+
+    #[test]
+    fn validate_chain_checks_links_and_work() {
+        let params = ConsensusParams::mainnet();
+        let first = mined_block(vec![coinbase(50)], 4).header().clone();
+        let mut second = BlockHeader::new(
+            first.hash(),
+            MerkleHash::new(crypto::hash(b"transactions")),
+            Timestamp::new(1),
+            4,
+            0,
+        );
+        second
+            .mine(&target_hash(4).unwrap(), &AtomicBool::new(false))
+            .unwrap();
+        assert!(BlockHeader::validate_chain(&[first.clone(), second.clone()], &params).is_ok());
+
+        assert!(matches!(
+            BlockHeader::validate_chain(&[second.clone(), first], &params),
+            Err(LuxError::InvalidBlock(_))
+        ));
+
+        let unmined = BlockHeader::new(
+            second.hash(),
+            MerkleHash::new(crypto::hash(b"transactions")),
+            Timestamp::new(2),
+            64,
+            0,
+        );
+        assert!(matches!(
+            BlockHeader::validate_chain(&[second, unmined], &params),
+            Err(LuxError::ProofOfWorkFailed { .. })
+        ));
+
+        // Difficulties come off the wire and may not fit a 256 bit target
+        let impossible = BlockHeader::new(
+            block_hash([0; 32]),
+            MerkleHash::new(crypto::hash(b"transactions")),
+            Timestamp::new(1),
+            u32::MAX,
+            0,
+        );
+        assert!(matches!(
+            BlockHeader::validate_chain(&[impossible], &params),
+            Err(LuxError::InvalidTarget(_))
+        ));
+    }
+
+    #[test]
+    fn block_hash_from_str() {
+        let hash = block_hash(*crypto::hash(b"block").as_ref());
+
+        assert_eq!(hash.to_string().parse::<BlockHash>().unwrap(), hash);
+        assert!(matches!(
+            "not a hash".parse::<BlockHash>(),
+            Err(LuxError::HashParse(_))
+        ));
+    }
+
+    #[test]
+    fn header_hash_is_pinned() {
+        let header = header();
+        let bytes = header.serialize_compact();
+
+        assert_eq!(&bytes[..4], &[1, 0, 0, 0]);
+        assert_eq!(&bytes[4..36], BlockHash::zero().as_slice());
+        assert_eq!(&bytes[36..68], crypto::hash(b"transactions").as_slice());
+        assert_eq!(
+            &bytes[68..],
+            &[0x00, 0x10, 0x5e, 0x5f, 8, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            header.hash().to_string(),
+            "03c61f8b4c53a9fb967baaef99f0877b26398ef1d574c3cb1b67378b425a96ee"
+        );
+    }
+
+    #[test]
+    fn meets_target_boundaries() {
+        let target = target_hash(8).unwrap();
+
+        let mut equal = [0xff; 32];
+        equal[0] = 0;
+        assert!(block_hash(equal).meets_target(&target));
+
+        let mut above = [0; 32];
+        above[0] = 1;
+        assert!(!block_hash(above).meets_target(&target));
+
+        assert!(block_hash([0; 32]).meets_target(&target));
+    }
+
+    #[test]
+    fn meets_target_least_significant_byte() {
+        let mut target = [0x00; 32];
+        target[31] = 0x10;
+        let target = block_hash(target);
+
+        let mut below = [0x00; 32];
+        below[31] = 0x0f;
+        let mut above = [0x00; 32];
+        above[31] = 0x11;
+
+        assert!(block_hash(below).meets_target(&target));
+        assert!(!block_hash(above).meets_target(&target));
+    }
+
+    #[test]
+    fn mine_finds_valid_nonce() {
+        let mut header = header();
+        let target = target_hash(header.difficulty()).unwrap();
+
+        let nonce = header.mine(&target, &AtomicBool::new(false)).unwrap();
+        assert_eq!(header.nonce(), nonce);
+        assert!(header.hash().meets_target(&target));
+        assert!(header.hash().as_ref().leading_zero_bits() >= header.difficulty());
+    }
+
+    #[test]
+    fn mine_can_be_cancelled() {
+        let mut header = header();
+        let target = target_hash(256 - 8).unwrap();
+
+        let result = header.mine(&target, &AtomicBool::new(true));
+        assert!(matches!(result, Err(LuxError::MiningCancelled)));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let transactions = vec![Transaction::new(
+            vec![TransactionInput::new_coinbase(0)],
+            vec![
+                TransactionOutput::new(Address::new("miner".to_string()), Luxcoin::new(50))
+                    .unwrap(),
+            ],
+            0,
+        )
+        .unwrap()];
+        let merkle_root = MerkleTree::from(&transactions).as_ref().clone();
+        let header = BlockHeader::new(
+            block_hash([0; 32]),
+            merkle_root,
+            Timestamp::new(1_600_000_000),
+            0,
+            0,
+        );
+        let block = Block::new(header, transactions);
+
+        let json = serde_json::to_string(&block).unwrap();
+        let decoded: Block = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded.id(), block.id());
+        assert_eq!(decoded.transactions()[0].id(), block.transactions()[0].id());
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+    }
+
+    #[test]
+    fn json_round_trip_checks_id_and_height() {
+        let block = fixtures::random_block(&BlockHash::zero());
+        let params = ConsensusParams::mainnet();
+
+        let json = block.to_json(Some(0));
+        let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(value["id"], block.id().to_string());
+        assert_eq!(value["height"], 0);
+        assert_eq!(
+            value["transactions"][1]["id"],
+            block.transactions()[1].id().to_string()
+        );
+        let decoded = Block::from_json(&json, &params).unwrap();
+        assert_eq!(decoded.id(), block.id());
+        assert_eq!(decoded.to_json(Some(0)), json);
+        let without_height = block.to_json(None);
+        assert!(serde_json::from_str::<serde_json::Value>(&without_height)
+            .unwrap()
+            .get("height")
+            .is_none());
+
+        assert!(matches!(
+            Block::from_json(&block.to_json(Some(1)), &params),
+            Err(LuxError::InvalidBlock(_))
+        ));
+        let mut tampered = value.clone();
+        tampered["id"] = serde_json::to_value(BlockHash::zero()).unwrap();
+        assert!(matches!(
+            Block::from_json(&tampered.to_string(), &params),
+            Err(LuxError::InvalidBlock(_))
+        ));
+    }
+
+    #[test]
+    fn genesis_is_deterministic() {
+        let miner = Address::new("miner".to_string());
+        let genesis = Block::genesis(
+            miner.clone(),
+            Luxcoin::new(50),
+            8,
+            &ConsensusParams::mainnet(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            Block::genesis_hash(
+                miner.clone(),
+                Luxcoin::new(50),
+                8,
+                &ConsensusParams::mainnet()
+            )
+            .unwrap(),
+            *genesis.id()
+        );
+        assert_ne!(
+            Block::genesis_hash(miner, Luxcoin::new(51), 8, &ConsensusParams::mainnet()).unwrap(),
+            *genesis.id()
+        );
+    }
+
+    #[test]
+    fn genesis_structure() {
+        let genesis = Block::genesis(
+            Address::new("miner".to_string()),
+            Luxcoin::new(50),
+            8,
+            &ConsensusParams::mainnet(),
+        )
+        .unwrap();
+        let header = genesis.header();
+
+        assert_eq!(header.previous_block_hash(), &BlockHash::zero());
+        assert_eq!(
+            header.merkle_root(),
+            MerkleTree::from(genesis.transactions()).root()
+        );
+        assert!(genesis.id().meets_target(&target_hash(8).unwrap()));
+        assert_eq!(genesis.transactions().len(), 1);
+        assert!(genesis.transactions()[0].is_coinbase());
+    }
+
+    #[test]
+    fn iter_outpoints_indexes_each_transaction() {
+        let reward = coinbase(50);
+        let split = Transaction::new(
+            vec![TransactionInput::new(*reward.id(), OutputIndex::new(0))],
+            vec![
+                TransactionOutput::new(Address::new("alice".to_string()), Luxcoin::new(20))
+                    .unwrap(),
+                TransactionOutput::new(Address::new("bob".to_string()), Luxcoin::new(30)).unwrap(),
+            ],
+            0,
+        )
+        .unwrap();
+        let block = mined_block(vec![reward.clone(), split.clone()], 0);
+
+        let outpoints = block
+            .iter_outpoints()
+            .map(|(id, index, output)| (id, index, output.amount()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outpoints,
+            vec![
+                (*reward.id(), OutputIndex::new(0), Luxcoin::new(50)),
+                (*split.id(), OutputIndex::new(0), Luxcoin::new(20)),
+                (*split.id(), OutputIndex::new(1), Luxcoin::new(30)),
+            ]
+        );
+    }
+
+    #[test]
+    fn display_summarizes_block() {
+        let genesis = Block::genesis(
+            Address::new("miner".to_string()),
+            "50".parse().unwrap(),
+            0,
+            &ConsensusParams::mainnet(),
+        )
+        .unwrap();
+        assert_eq!(genesis.height(), Some(0));
+        assert_eq!(
+            genesis.to_string(),
+            format!(
+                "block {} at height 0, previous: {}, time: 2021-11-06T00:00:00Z, transactions: 1, output: 50 LUX",
+                genesis.id(),
+                BlockHash::zero()
+            )
+        );
+
+        let block = mined_block(vec![payment()], 0);
+        assert_eq!(block.height(), None);
+        assert!(block
+            .to_string()
+            .ends_with("transactions: 1, output: 0.0000005 LUX"));
+    }
+
+    #[test]
+    fn header_display_lists_fields() {
+        let lines = header().to_string();
+        let lines = lines.lines().collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "version:        1");
+        assert_eq!(lines[1], format!("previous block: {}", BlockHash::zero()));
+        assert_eq!(lines[3], "timestamp:      2020-09-13T12:26:40Z");
+        assert_eq!(lines[4], "difficulty:     8");
+    }
+
+    #[test]
+    fn validate_accepts_valid_block() {
+        let block = mined_block(vec![coinbase(50), payment()], 8);
+        assert!(block.validate(&ConsensusParams::mainnet()).is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_unknown_version() {
+        let transactions = vec![coinbase(50)];
+        let merkle_root = MerkleTree::from(&transactions).root().clone();
+        let mut header = BlockHeader::with_version(
+            BLOCK_VERSION + 1,
+            BlockHash::zero(),
+            merkle_root,
+            Timestamp::new(0),
+            8,
+            0,
+        );
+        header
+            .mine(&target_hash(8).unwrap(), &AtomicBool::new(false))
+            .unwrap();
+        let block = Block::new(header, transactions);
+
+        assert!(matches!(
+            block.validate(&ConsensusParams::mainnet()),
+            Err(LuxError::InvalidBlock(_))
+        ));
+    }
+
+    #[test]
+    fn serialized_size_matches_encoding() {
+        let block = mined_block(vec![coinbase(50), payment()], 0);
+        assert_eq!(
+            block.serialized_size(),
+            bincode::serialize(&block).unwrap().len()
+        );
+        assert_eq!(
+            block.transactions()[1].serialized_size(),
+            bincode::serialize(&block.transactions()[1]).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn validate_size_enforces_limit() {
+        let block = mined_block(vec![coinbase(50), payment()], 0);
+        let size = block.serialized_size();
+
+        assert!(block.validate_size(size).is_ok());
+        assert!(matches!(
+            block.validate_size(size - 1),
+            Err(LuxError::InvalidBlock(_))
+        ));
+    }
+
+    #[test]
+    fn weight_scales_size_and_is_enforced() {
+        let block = mined_block(vec![coinbase(50), payment()], 0);
+        let weight = block.weight();
+        assert_eq!(
+            weight,
+            block.serialized_size() as u64 * WITNESS_SCALE_FACTOR
+        );
+
+        assert!(block.validate_weight(weight).is_ok());
+        let params = ConsensusParams {
+            max_block_weight: weight - 1,
+            ..ConsensusParams::mainnet()
+        };
+        assert!(matches!(
+            block.validate(&params),
+            Err(LuxError::InvalidBlock(_))
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_insufficient_work() {
+        let transactions = vec![coinbase(50)];
+        let merkle_root = MerkleTree::from(&transactions).root().clone();
+        let header = BlockHeader::new(BlockHash::zero(), merkle_root, Timestamp::new(0), 64, 0);
+        let block = Block::new(header, transactions);
+
+        assert!(matches!(
+            block.validate(&ConsensusParams::mainnet()),
+            Err(LuxError::ProofOfWorkFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn validate_uses_pow_algorithm() {
+        let transactions = vec![coinbase(50)];
+        let merkle_root = MerkleTree::from(&transactions).root().clone();
+        let mut header = BlockHeader::new(BlockHash::zero(), merkle_root, Timestamp::new(0), 8, 0);
+        header
+            .mine_with(
+                &Sha256Algorithm,
+                &target_hash(8).unwrap(),
+                &AtomicBool::new(false),
+            )
+            .unwrap();
+        let block = Block::new(header, transactions);
+        let params = ConsensusParams {
+            pow_algorithm: &Sha256Algorithm,
+            ..ConsensusParams::mainnet()
+        };
+
+        assert!(block.validate(&params).is_ok());
+        assert_eq!(block.id(), &block.header().hash());
+    }
+
+    #[test]
+    fn validate_rejects_oversized_block() {
+        let block = mined_block(vec![coinbase(50), payment()], 0);
+        let params = ConsensusParams {
+            max_block_size: block.serialized_size() - 1,
+            ..ConsensusParams::mainnet()
+        };
+
+        assert!(matches!(
+            block.validate(&params),
+            Err(LuxError::InvalidBlock(_))
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_merkle_root_mismatch() {
+        let block = mined_block(vec![coinbase(50), payment()], 0);
+        let tampered = Block::new(block.header().clone(), vec![coinbase(50)]);
+        assert!(block.merkle_root_matches());
+        assert!(!tampered.merkle_root_matches());
+        assert_eq!(
+            Block::new(block.header().clone(), vec![]).compute_merkle_root(),
+            MerkleTree::empty_root()
+        );
+
+        assert!(matches!(
+            tampered.validate(&ConsensusParams::mainnet()),
+            Err(LuxError::MerkleRootMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_duplicates_and_double_spends() {
+        let params = ConsensusParams::mainnet();
+        assert!(matches!(
+            mined_block(vec![coinbase(50), payment(), payment()], 0).validate(&params),
+            Err(LuxError::InvalidBlock(_))
+        ));
+
+        let conflicting = Transaction::new(
+            payment().inputs().clone(),
+            vec![
+                TransactionOutput::new(Address::new("bob".to_string()), Luxcoin::new(50)).unwrap(),
+            ],
+            0,
+        )
+        .unwrap();
+        assert!(matches!(
+            mined_block(vec![coinbase(50), payment(), conflicting], 0).validate(&params),
+            Err(LuxError::DoubleSpend { .. })
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_bad_coinbase() {
+        let params = ConsensusParams::mainnet();
+        let empty = BlockHeader::new(
+            BlockHash::zero(),
+            MerkleHash::new(crypto::hash(b"transactions")),
+            Timestamp::new(0),
+            0,
+            0,
+        );
+
+        assert!(matches!(
+            Block::new(empty, vec![]).validate(&params),
+            Err(LuxError::InvalidBlock(_))
+        ));
+        assert!(matches!(
+            mined_block(vec![payment(), coinbase(50)], 0).validate(&params),
+            Err(LuxError::InvalidCoinbase(_))
+        ));
+        assert!(matches!(
+            mined_block(vec![coinbase(50), coinbase(25)], 0).validate(&params),
+            Err(LuxError::InvalidCoinbase(_))
+        ));
+    }
 }
@@ -0,0 +1,328 @@
+use std::{collections::HashSet, sync::atomic::AtomicBool};
+
+use crate::core::{
+    address::Address,
+    block::{Block, BlockHeader},
+    blockchain::Blockchain,
+    consensus::ConsensusParams,
+    crypto::MerkleTree,
+    difficulty, economics,
+    error::{LuxError, LuxResult},
+    fee_rate::FeeRate,
+    luxcoin::Luxcoin,
+    mempool::Mempool,
+    store::BlockStore,
+    timestamp::{Timestamp, MEDIAN_TIME_SPAN},
+    transaction::{Transaction, TransactionId},
+};
+
+// A block ready to be mined on top of the current tip: everything but the
+// nonce is filled in
+pub struct BlockTemplate {
+    header: BlockHeader,
+    transactions: Vec<Transaction>,
+}
+
+impl BlockTemplate {
+    // Takes mempool transactions, highest fee rate first, for as long as the
+    // block stays within `params.max_block_size` and `params.max_block_weight`,
+    // and pays the subsidy plus their fees to `miner_address`. Transactions
+    // that no longer apply on top of the tip, or aren't final yet, are skipped.
+    // The coinbase comes first and the rest follow `order_transactions`, so
+    // the same mempool always yields the same Merkle root.
+    pub fn build<S: BlockStore>(
+        chain: &Blockchain<S>,
+        mempool: &Mempool,
+        miner_address: Address,
+        params: &ConsensusParams,
+    ) -> LuxResult<Self> {
+        let height = chain.height() + 1;
+        let timestamp = next_timestamp(chain);
+        let difficulty = next_difficulty(chain, params);
+
+        // Coinbase amounts have a fixed width encoding, so the fees don't
+        // change the size of the coinbase only block
+        let placeholder = Transaction::new_coinbase(
+            miner_address.clone(),
+            economics::block_subsidy(height, params),
+            Luxcoin::ZERO,
+            height,
+        )?;
+        let placeholder_block = Block::new(chain.tip().header().clone(), vec![placeholder]);
+        let mut size = placeholder_block.serialized_size();
+        let mut weight = placeholder_block.weight();
+
+        let mut selected = vec![];
+        let mut fees = Luxcoin::ZERO;
+        for transaction in mempool.select_for_block(usize::MAX) {
+            if !transaction.is_final(height, timestamp) {
+                continue;
+            }
+            let fee = match transaction.fee(chain.utxos()) {
+                Ok(fee) => fee,
+                Err(_) => continue,
+            };
+            let transaction_size = transaction.serialized_size();
+            let transaction_weight = transaction.weight();
+            if size + transaction_size > params.max_block_size
+                || weight + transaction_weight > params.max_block_weight
+            {
+                continue;
+            }
+            let total_fees = match fees.checked_add(fee) {
+                Some(total_fees) => total_fees,
+                None => continue,
+            };
+
+            size += transaction_size;
+            weight += transaction_weight;
+            fees = total_fees;
+            selected.push((
+                transaction.clone(),
+                FeeRate::new(fee, transaction_weight as usize),
+            ));
+        }
+
+        let coinbase = Transaction::new_coinbase(
+            miner_address,
+            economics::block_subsidy(height, params),
+            fees,
+            height,
+        )?;
+        let mut transactions = vec![coinbase];
+        transactions.extend(order_transactions(selected)?);
+
+        let merkle_root = MerkleTree::from(&transactions).root().clone();
+        let header = BlockHeader::new(*chain.tip().id(), merkle_root, timestamp, difficulty, 0);
+        Ok(Self {
+            header,
+            transactions,
+        })
+    }
+
+    pub fn header(&self) -> &BlockHeader {
+        &self.header
+    }
+
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    // Searches for a nonce meeting the template's difficulty, see
+    // `BlockHeader::mine_with`
+    pub fn mine(mut self, params: &ConsensusParams, cancel: &AtomicBool) -> LuxResult<Block> {
+        let target = self.header.target()?;
+        self.header
+            .mine_with(params.pow_algorithm, &target, cancel)?;
+        Ok(Block::new(self.header, self.transactions))
+    }
+}
+
+// Orders transactions so that any spending another one in the list comes after
+// it, picking the highest fee rate among the ready ones and breaking ties by
+// id. Fails on a dependency cycle, which valid transactions can't form since
+// ids commit to the outputs they spend.
+fn order_transactions(mut pending: Vec<(Transaction, FeeRate)>) -> LuxResult<Vec<Transaction>> {
+    pending.sort_by(|(lhs, lhs_rate), (rhs, rhs_rate)| {
+        rhs_rate
+            .cmp(lhs_rate)
+            .then_with(|| lhs.id().as_ref().cmp(rhs.id().as_ref()))
+    });
+    let ids = pending
+        .iter()
+        .map(|(transaction, _)| *transaction.id())
+        .collect::<HashSet<TransactionId>>();
+
+    let mut placed = HashSet::new();
+    let mut ordered = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|(transaction, _)| {
+            transaction
+                .inputs()
+                .iter()
+                .all(|input| !ids.contains(input.utxo_id()) || placed.contains(input.utxo_id()))
+        });
+        let (transaction, _) = match ready {
+            Some(index) => pending.remove(index),
+            None => {
+                return Err(LuxError::InvalidBlock(format!(
+                    "{} transactions depend on each other in a cycle.",
+                    pending.len()
+                )))
+            }
+        };
+        placed.insert(*transaction.id());
+        ordered.push(transaction);
+    }
+
+    Ok(ordered)
+}
+
+// The current time, unless that isn't after the median time past of the tip
+fn next_timestamp<S: BlockStore>(chain: &Blockchain<S>) -> Timestamp {
+    let median = chain.median_time_past(MEDIAN_TIME_SPAN);
+    let now = Timestamp::now();
+    if now > median {
+        now
+    } else {
+        median.checked_add_secs(1).unwrap_or(median)
+    }
+}
+
+// The difficulty `Blockchain` expects of a block on top of the tip
+fn next_difficulty<S: BlockStore>(chain: &Blockchain<S>, params: &ConsensusParams) -> u32 {
+    let window = || {
+        let first = (chain.height() + 1).saturating_sub(params.difficulty_window as u32);
+        (first..=chain.height())
+            .filter_map(|height| chain.get_header_at(height))
+            .cloned()
+            .collect::<Vec<BlockHeader>>()
+    };
+
+    difficulty::expected_difficulty(chain.height() + 1, chain.tip().header(), window, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use super::{order_transactions, BlockTemplate};
+    use crate::core::{
+        address::Address,
+        block::Block,
+        blockchain::Blockchain,
+        consensus::ConsensusParams,
+        economics,
+        fee_rate::FeeRate,
+        fixtures,
+        luxcoin::Luxcoin,
+        mempool::Mempool,
+        transaction::{OutputIndex, Transaction, TransactionInput, TransactionOutput},
+    };
+
+    fn setup() -> (Blockchain, Mempool, Transaction) {
+        let genesis = Block::genesis(
+            fixtures::address("genesis"),
+            Luxcoin::new(50),
+            0,
+            &ConsensusParams::mainnet(),
+        )
+        .unwrap();
+        let chain = Blockchain::new(genesis.clone(), ConsensusParams::mainnet()).unwrap();
+
+        let spend = Transaction::new(
+            vec![TransactionInput::new(
+                *genesis.transactions()[0].id(),
+                OutputIndex::new(0),
+            )],
+            vec![
+                TransactionOutput::new(Address::new("alice".to_string()), Luxcoin::new(45))
+                    .unwrap(),
+            ],
+            0,
+        )
+        .unwrap();
+        let spent = &genesis.transactions()[0].outputs()[0];
+        let spend = fixtures::sign(spend, "genesis", &[spent]);
+        let mut mempool = Mempool::new();
+        mempool.add(spend.clone(), chain.utxos()).unwrap();
+
+        (chain, mempool, spend)
+    }
+
+    #[test]
+    fn builds_a_valid_block_paying_fees() {
+        let (mut chain, mempool, spend) = setup();
+        let params = ConsensusParams::mainnet();
+        let miner = Address::new("miner".to_string());
+
+        let template = BlockTemplate::build(&chain, &mempool, miner.clone(), &params).unwrap();
+        assert_eq!(template.header().previous_block_hash(), chain.tip().id());
+        assert_eq!(template.transactions().len(), 2);
+        assert_eq!(&template.transactions()[1], &spend);
+
+        let coinbase = &template.transactions()[0];
+        assert_eq!(coinbase.outputs()[0].to(), Some(&miner));
+        assert_eq!(
+            coinbase.total_output(),
+            economics::block_subsidy(1, &params) + Luxcoin::new(5)
+        );
+
+        let block = template.mine(&params, &AtomicBool::new(false)).unwrap();
+        chain.append(block).unwrap();
+        assert_eq!(chain.height(), 1);
+    }
+
+    #[test]
+    fn difficulty_only_changes_between_windows() {
+        let params = ConsensusParams {
+            difficulty_window: 2,
+            ..ConsensusParams::mainnet()
+        };
+        let genesis = Block::genesis(
+            Address::new("genesis".to_string()),
+            Luxcoin::new(50),
+            4,
+            &params,
+        )
+        .unwrap();
+        let mut chain = Blockchain::new(genesis, params.clone()).unwrap();
+        let miner = Address::new("miner".to_string());
+
+        let mut difficulties = vec![];
+        for _ in 0..4 {
+            let template =
+                BlockTemplate::build(&chain, &Mempool::new(), miner.clone(), &params).unwrap();
+            let block = template.mine(&params, &AtomicBool::new(false)).unwrap();
+            difficulties.push(block.header().difficulty());
+            chain.append(block).unwrap();
+        }
+
+        assert_eq!(chain.height(), 4);
+        assert_eq!(difficulties[0], 4);
+        assert_eq!(difficulties[2], difficulties[1]);
+    }
+
+    #[test]
+    fn leaves_out_transactions_over_the_size_limit() {
+        let (chain, mempool, _) = setup();
+        let params = ConsensusParams {
+            max_block_size: 1,
+            ..ConsensusParams::mainnet()
+        };
+
+        let template =
+            BlockTemplate::build(&chain, &mempool, Address::new("miner".to_string()), &params)
+                .unwrap();
+        assert_eq!(template.transactions().len(), 1);
+        assert_eq!(
+            template.transactions()[0].total_output(),
+            economics::block_subsidy(1, &params)
+        );
+    }
+
+    #[test]
+    fn orders_dependencies_then_fee_rate() {
+        let parent = fixtures::random_transaction();
+        let child = Transaction::new(
+            vec![TransactionInput::new(*parent.id(), OutputIndex::new(0))],
+            vec![
+                TransactionOutput::new(Address::new("bob".to_string()), Luxcoin::new(40)).unwrap(),
+            ],
+            0,
+        )
+        .unwrap();
+        let unrelated = fixtures::random_transaction();
+        let rate = |fee| FeeRate::new(Luxcoin::new(fee), 100);
+        let selected = vec![
+            (parent.clone(), rate(1)),
+            (child.clone(), rate(10)),
+            (unrelated.clone(), rate(5)),
+        ];
+
+        let expected = vec![unrelated, parent, child];
+        assert_eq!(order_transactions(selected.clone()).unwrap(), expected);
+        let reversed = selected.into_iter().rev().collect();
+        assert_eq!(order_transactions(reversed).unwrap(), expected);
+    }
+}
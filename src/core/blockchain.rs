@@ -0,0 +1,1331 @@
+use std::collections::{HashMap, HashSet};
+
+use primitive_types::U256;
+
+use crate::core::{
+    block::{Block, BlockHash, BlockHeader},
+    consensus::ConsensusParams,
+    crypto, difficulty, economics,
+    error::{LuxError, LuxResult},
+    luxcoin::Luxcoin,
+    store::{BlockStore, MemoryBlockStore},
+    timestamp::{self, Timestamp, MEDIAN_TIME_SPAN},
+    transaction::{OutputIndex, TransactionId, TransactionOutput},
+    utxo::{OutPoint, UtxoSet},
+};
+
+// Expected number of hashes needed to meet `target`, 2^256 / (target + 1).
+// Saturates for the zero target, whose work doesn't fit in 256 bits.
+pub fn target_work(target: &BlockHash) -> U256 {
+    let target = U256::from_big_endian(target.as_slice());
+    match target.checked_add(U256::one()) {
+        // 2^256 itself doesn't fit either, but (2^256 - target - 1) / (target + 1)
+        // is one less than the work
+        Some(divisor) => (!target / divisor).saturating_add(U256::one()),
+        None => U256::one(),
+    }
+}
+
+// Work of a block mined at `difficulty`, 2^difficulty
+pub fn block_work(difficulty: u32) -> LuxResult<U256> {
+    Ok(target_work(&crypto::target_hash(difficulty)?))
+}
+
+// Number of most recent blocks a block locator lists one by one before the
+// gaps start doubling
+pub const LOCATOR_DENSE_SPAN: usize = 10;
+
+// Most blocks with an unknown parent kept around at once
+pub const MAX_ORPHAN_BLOCKS: usize = 100;
+
+struct ChainEntry {
+    // Without transactions once the block is pruned
+    block: Block,
+    height: u32,
+    // Accumulated work from genesis up to and including this block
+    work: U256,
+}
+
+// Every known block, arranged as a tree rooted at genesis. The canonical chain
+// is the branch with the most accumulated work; the UTXO set always reflects
+// its tip. Accepted blocks and the canonical chain are written through to the
+// store.
+pub struct Blockchain<S: BlockStore = MemoryBlockStore> {
+    entries: HashMap<BlockHash, ChainEntry>,
+    // Blocks no other known block builds on
+    tips: HashSet<BlockHash>,
+    // Block hashes of the canonical chain, indexed by height
+    heights: Vec<BlockHash>,
+    // Blocks received before their parent, waiting for it to arrive
+    orphans: HashMap<BlockHash, Block>,
+    // Canonical blocks below this height only have their header kept
+    pruned_height: u32,
+    // Canonical blocks below this height can't be reverted anymore, so the
+    // UTXO set no longer keeps their undo data
+    final_height: u32,
+    utxos: UtxoSet,
+    params: ConsensusParams,
+    store: S,
+}
+
+impl Blockchain {
+    pub fn new(genesis: Block, params: ConsensusParams) -> LuxResult<Self> {
+        Self::open(MemoryBlockStore::new(), genesis, params)
+    }
+}
+
+impl<S: BlockStore> Blockchain<S> {
+    // Starts a chain at `genesis`, then replays the canonical chain already in
+    // the store, if any. Only the canonical chain is restored; side branches
+    // have to be received again.
+    pub fn open(mut store: S, genesis: Block, params: ConsensusParams) -> LuxResult<Self> {
+        // The UTXO set is rebuilt from the stored blocks, which pruning removed
+        let pruned_height = store.pruned_height()?;
+        if pruned_height > 0 {
+            return Err(LuxError::Storage(format!(
+                "Stored chain is pruned below height: {} and can't be replayed.",
+                pruned_height
+            )));
+        }
+
+        match store.get_by_height(0)? {
+            Some(stored) if stored.id() != genesis.id() => {
+                return Err(LuxError::Storage(format!(
+                    "Stored genesis block: {} doesn't match: {}.",
+                    stored.id(),
+                    genesis.id()
+                )))
+            }
+            Some(_) => {}
+            None => {
+                store.put_block(&genesis)?;
+                store.set_tip(0, genesis.id())?;
+            }
+        }
+
+        // Appending moves the stored tip, so the stored chain is read up front
+        let mut stored = vec![];
+        while let Some(block) = store.get_by_height(stored.len() as u32 + 1)? {
+            stored.push(block);
+        }
+
+        let mut blockchain = Self::from_genesis(store, genesis, params)?;
+        for block in stored {
+            blockchain.append(block)?;
+        }
+
+        Ok(blockchain)
+    }
+
+    fn from_genesis(store: S, genesis: Block, params: ConsensusParams) -> LuxResult<Self> {
+        if genesis.header().previous_block_hash() != &BlockHash::zero() {
+            return Err(LuxError::InvalidBlock(format!(
+                "Block: {} is not a genesis block.",
+                genesis.id()
+            )));
+        }
+        genesis.validate(&params)?;
+        validate_coinbase_height(&genesis, 0)?;
+
+        let mut utxos = UtxoSet::new();
+        validate_finality(&genesis, 0)?;
+        validate_amounts(&utxos, &genesis, 0, &params)?;
+        utxos.apply_block(&genesis)?;
+
+        let id = *genesis.id();
+        let work = block_work(genesis.header().difficulty())?;
+        let entry = ChainEntry {
+            block: genesis,
+            height: 0,
+            work,
+        };
+        Ok(Self {
+            entries: HashMap::from([(id, entry)]),
+            tips: HashSet::from([id]),
+            heights: vec![id],
+            orphans: HashMap::new(),
+            pruned_height: 0,
+            final_height: 0,
+            utxos,
+            params,
+            store,
+        })
+    }
+
+    // Adds a block built on any known block, then switches to its branch if
+    // that branch now has the most accumulated work. A block whose parent is
+    // unknown is kept as an orphan, and connected along with any orphans
+    // building on it once the parent arrives.
+    pub fn append(&mut self, block: Block) -> LuxResult<()> {
+        if self.entries.contains_key(block.id()) || self.orphans.contains_key(block.id()) {
+            return Err(LuxError::InvalidBlock(format!(
+                "Block: {} is already known.",
+                block.id()
+            )));
+        }
+        if !self
+            .entries
+            .contains_key(block.header().previous_block_hash())
+        {
+            block.validate(&self.params)?;
+            self.add_orphan(block);
+            return Ok(());
+        }
+
+        let id = *block.id();
+        self.connect(block)?;
+        self.connect_orphans(id);
+        Ok(())
+    }
+
+    // Keeps a block until its parent arrives. When the pool is full an
+    // arbitrary orphan makes room, so a peer can't tell which ones survive.
+    fn add_orphan(&mut self, block: Block) {
+        if self.orphans.len() >= MAX_ORPHAN_BLOCKS {
+            if let Some(evicted) = self.orphans.keys().next().copied() {
+                self.orphans.remove(&evicted);
+            }
+        }
+        self.orphans.insert(*block.id(), block);
+    }
+
+    // Connects the orphans building on `parent`, then the ones building on
+    // those, and so on. Orphans that fail to connect are dropped.
+    fn connect_orphans(&mut self, parent: BlockHash) {
+        let mut parents = vec![parent];
+        while let Some(parent) = parents.pop() {
+            let children = self
+                .orphans
+                .iter()
+                .filter(|(_, block)| block.header().previous_block_hash() == &parent)
+                .map(|(id, _)| *id)
+                .collect::<Vec<BlockHash>>();
+            for child in children {
+                let block = self.orphans.remove(&child).expect("child is an orphan");
+                if self.connect(block).is_ok() {
+                    parents.push(child);
+                }
+            }
+        }
+    }
+
+    // Adds a block whose parent is known
+    fn connect(&mut self, block: Block) -> LuxResult<()> {
+        let previous = *block.header().previous_block_hash();
+        let parent = &self.entries[&previous];
+        let height = parent.height + 1;
+        validate_checkpoint(&self.params, height, block.id())?;
+        if let Some((checkpoint, _)) = self.last_checkpoint() {
+            if height <= checkpoint {
+                return Err(LuxError::InvalidBlock(format!(
+                    "Block: {} forks off below the checkpoint at height: {}.",
+                    block.id(),
+                    checkpoint
+                )));
+            }
+        }
+        block.validate(&self.params)?;
+        validate_coinbase_height(&block, height)?;
+        self.validate_difficulty(&block, height)?;
+        block
+            .header()
+            .timestamp()
+            .validate_after(&self.ancestor_timestamps(&previous, MEDIAN_TIME_SPAN))?;
+        self.store.put_block(&block)?;
+
+        let id = *block.id();
+        let entry = ChainEntry {
+            height,
+            work: parent
+                .work
+                .saturating_add(block_work(block.header().difficulty())?),
+            block,
+        };
+        self.tips.remove(&previous);
+        self.tips.insert(id);
+        self.entries.insert(id, entry);
+
+        self.reorganize()
+    }
+
+    // Makes the candidate tip with the most accumulated work canonical, rolling
+    // the UTXO set back to the fork point and replaying the new branch. Ties
+    // keep the current tip. If a block on the new branch fails to apply, it and
+    // its descendants are discarded and the previous chain is restored.
+    pub fn reorganize(&mut self) -> LuxResult<()> {
+        let tip = *self.tip().id();
+        let best = self.tips.iter().fold(tip, |best, candidate| {
+            if self.entries[candidate].work > self.entries[&best].work {
+                *candidate
+            } else {
+                best
+            }
+        });
+        if best == tip {
+            return Ok(());
+        }
+
+        let mut branch = vec![];
+        let mut fork = best;
+        while !self.is_canonical(&fork) {
+            branch.push(fork);
+            fork = *self.entries[&fork].block.header().previous_block_hash();
+        }
+        branch.reverse();
+
+        // Reverting a pruned block needs the transactions that are gone
+        if self.entries[&fork].height + 1 < self.pruned_height {
+            self.discard(&branch[0]);
+            return Err(LuxError::InvalidBlock(format!(
+                "Branch: {} forks off below the pruned height: {}.",
+                best, self.pruned_height
+            )));
+        }
+
+        let abandoned = self
+            .heights
+            .split_off(self.entries[&fork].height as usize + 1);
+        for hash in abandoned.iter().rev() {
+            self.utxos.revert_block(&self.entries[hash].block)?;
+        }
+
+        for (i, hash) in branch.iter().enumerate() {
+            let entry = &self.entries[hash];
+            let applied = validate_finality(&entry.block, entry.height)
+                .and_then(|_| {
+                    validate_amounts(&self.utxos, &entry.block, entry.height, &self.params)
+                })
+                .and_then(|_| self.utxos.apply_block(&entry.block));
+            if let Err(e) = applied {
+                for applied in branch[..i].iter().rev() {
+                    self.utxos.revert_block(&self.entries[applied].block)?;
+                }
+                self.heights
+                    .truncate(self.entries[&fork].height as usize + 1);
+                for hash in &abandoned {
+                    self.utxos.apply_block(&self.entries[hash].block)?;
+                }
+                self.heights.extend(abandoned);
+                self.discard(hash);
+
+                return Err(e);
+            }
+            self.heights.push(*hash);
+        }
+
+        for hash in &branch {
+            self.store.set_tip(self.entries[hash].height, hash)?;
+        }
+        self.forget_final_undo();
+
+        Ok(())
+    }
+
+    // Drops the undo data of canonical blocks no reorganization can reach:
+    // those below the pruned height and those up to the last checkpoint
+    fn forget_final_undo(&mut self) {
+        let checkpointed = self.last_checkpoint().map_or(0, |(height, _)| height + 1);
+        let final_height = checkpointed.max(self.pruned_height);
+        if final_height <= self.final_height {
+            return;
+        }
+
+        for hash in &self.heights[self.final_height as usize..final_height as usize] {
+            self.utxos.forget_undo(hash);
+        }
+        self.final_height = final_height;
+    }
+
+    // Drops a block that isn't part of the canonical chain, along with every
+    // block built on it
+    fn discard(&mut self, hash: &BlockHash) {
+        let children = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.block.header().previous_block_hash() == hash)
+            .map(|(child, _)| *child)
+            .collect::<Vec<BlockHash>>();
+        children.iter().for_each(|child| self.discard(child));
+
+        if let Some(entry) = self.entries.remove(hash) {
+            self.tips.remove(hash);
+            let parent = entry.block.header().previous_block_hash();
+            let has_children = self
+                .entries
+                .values()
+                .any(|entry| entry.block.header().previous_block_hash() == parent);
+            if !has_children {
+                self.tips.insert(*parent);
+            }
+        }
+    }
+
+    // Headers of the block `hash` and its ancestors, newest first, at most
+    // `count` of them
+    fn ancestor_headers(&self, hash: &BlockHash, count: usize) -> Vec<BlockHeader> {
+        let mut headers = vec![];
+        let mut current = self.entries.get(hash);
+        while let Some(entry) = current.filter(|_| headers.len() < count) {
+            headers.push(entry.block.header().clone());
+            current = self.entries.get(entry.block.header().previous_block_hash());
+        }
+        headers
+    }
+
+    // Timestamps of the block `hash` and its ancestors, newest first, at most
+    // `count` of them
+    fn ancestor_timestamps(&self, hash: &BlockHash, count: usize) -> Vec<Timestamp> {
+        self.ancestor_headers(hash, count)
+            .iter()
+            .map(BlockHeader::timestamp)
+            .collect()
+    }
+
+    // Fails unless the block declares the difficulty its branch calls for at
+    // `height`, see `difficulty::expected_difficulty`
+    fn validate_difficulty(&self, block: &Block, height: u32) -> LuxResult<()> {
+        let previous = block.header().previous_block_hash();
+        let window = || {
+            let mut headers = self.ancestor_headers(previous, self.params.difficulty_window);
+            headers.reverse();
+            headers
+        };
+        let parent = self.entries[previous].block.header();
+        let expected = difficulty::expected_difficulty(height, parent, window, &self.params);
+
+        let actual = block.header().difficulty();
+        if actual != expected {
+            return Err(LuxError::DifficultyMismatch {
+                height,
+                expected,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    // Walks the canonical chain from genesis to the tip, checking that every
+    // block links to the one before it, matches the checkpoints and passes
+    // `Block::validate` under `params`. Only the proof of work of pruned
+    // blocks can still be checked. Meant to run once a chain has been loaded
+    // from disk.
+    pub fn verify_all(&self, params: &ConsensusParams) -> LuxResult<()> {
+        let mut previous = BlockHash::zero();
+        for (height, hash) in self.heights.iter().enumerate() {
+            let block = &self.entries[hash].block;
+            let verified = if block.header().previous_block_hash() != &previous {
+                Err(LuxError::InvalidBlock(format!(
+                    "Block: {} doesn't build on: {}.",
+                    block.id(),
+                    previous
+                )))
+            } else if (height as u32) < self.pruned_height {
+                BlockHeader::validate_chain(std::slice::from_ref(block.header()), params)
+            } else {
+                block
+                    .validate(params)
+                    .and_then(|_| validate_coinbase_height(block, height as u32))
+            }
+            .and_then(|_| validate_checkpoint(params, height as u32, hash));
+            verified.map_err(|e| LuxError::ChainVerification {
+                height: height as u32,
+                reason: e.to_string(),
+            })?;
+            previous = *hash;
+        }
+
+        Ok(())
+    }
+
+    fn is_canonical(&self, hash: &BlockHash) -> bool {
+        self.heights.get(self.entries[hash].height as usize) == Some(hash)
+    }
+
+    // Canonical block hashes from the tip back to genesis, sent to a peer so
+    // it can find where its chain and this one diverge. The last
+    // `LOCATOR_DENSE_SPAN` blocks are all listed, then the gap doubles with
+    // every entry. Genesis always comes last.
+    pub fn block_locator(&self) -> Vec<BlockHash> {
+        let mut locator = vec![];
+        let mut height = self.height();
+        let mut step = 1;
+        while height > 0 {
+            locator.push(self.heights[height as usize]);
+            if locator.len() >= LOCATOR_DENSE_SPAN {
+                step *= 2;
+            }
+            height = height.saturating_sub(step);
+        }
+        locator.push(self.heights[0]);
+        locator
+    }
+
+    // The first block of a peer's locator that is on this node's canonical
+    // chain, i.e. the most recent block both chains share
+    pub fn find_fork_point(&self, locator: &[BlockHash]) -> Option<BlockHash> {
+        locator
+            .iter()
+            .find(|hash| self.entries.contains_key(hash) && self.is_canonical(hash))
+            .copied()
+    }
+
+    // Highest checkpoint the canonical chain has reached. Blocks are checked
+    // against the checkpoints as they connect, so the chain always matches it.
+    pub fn last_checkpoint(&self) -> Option<(u32, BlockHash)> {
+        self.params
+            .checkpoints
+            .iter()
+            .filter(|(height, _)| *height <= self.height())
+            .max_by_key(|(height, _)| *height)
+            .copied()
+    }
+
+    // Height of the tip, the genesis block being at height 0
+    pub fn height(&self) -> u32 {
+        (self.heights.len() - 1) as u32
+    }
+
+    pub fn tip(&self) -> &Block {
+        &self.entries[self.heights.last().unwrap()].block
+    }
+
+    // Median timestamp of the last `count` canonical blocks, the tip included.
+    // Chains shorter than that use every block they have. New blocks must be
+    // timestamped after the median of the `MEDIAN_TIME_SPAN` blocks before them.
+    pub fn median_time_past(&self, count: usize) -> Timestamp {
+        let timestamps = self.ancestor_timestamps(self.heights.last().unwrap(), count.max(1));
+        timestamp::median(&timestamps).expect("the chain always has a genesis block")
+    }
+
+    // Accumulated work of the canonical chain
+    pub fn work(&self) -> U256 {
+        self.entries[self.heights.last().unwrap()].work
+    }
+
+    // Accumulated work from genesis up to and including any known block
+    pub fn chain_work(&self, hash: &BlockHash) -> Option<U256> {
+        self.entries.get(hash).map(|entry| entry.work)
+    }
+
+    // Number of blocks waiting for their parent
+    pub fn orphan_count(&self) -> usize {
+        self.orphans.len()
+    }
+
+    // Every block no other known block builds on, the canonical tip included
+    pub fn tips(&self) -> &HashSet<BlockHash> {
+        &self.tips
+    }
+
+    pub fn utxos(&self) -> &UtxoSet {
+        &self.utxos
+    }
+
+    pub fn params(&self) -> &ConsensusParams {
+        &self.params
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    // Looks up any known block, whether or not it is on the canonical chain.
+    // Fails with `LuxError::BlockPruned` for blocks whose transactions were
+    // pruned.
+    pub fn get_block(&self, hash: &BlockHash) -> LuxResult<Option<&Block>> {
+        match self.entries.get(hash) {
+            Some(entry) if self.is_pruned(entry) => Err(LuxError::BlockPruned(*hash)),
+            entry => Ok(entry.map(|entry| &entry.block)),
+        }
+    }
+
+    pub fn get_block_at(&self, height: u32) -> LuxResult<Option<&Block>> {
+        match self.heights.get(height as usize) {
+            Some(hash) => self.get_block(hash),
+            None => Ok(None),
+        }
+    }
+
+    // Headers stay available after pruning
+    pub fn get_header(&self, hash: &BlockHash) -> Option<&BlockHeader> {
+        self.entries.get(hash).map(|entry| entry.block.header())
+    }
+
+    pub fn get_header_at(&self, height: u32) -> Option<&BlockHeader> {
+        self.heights
+            .get(height as usize)
+            .and_then(|hash| self.get_header(hash))
+    }
+
+    pub fn pruned_height(&self) -> u32 {
+        self.pruned_height
+    }
+
+    // Drops the transactions of every canonical block more than `keep_depth`
+    // blocks below the tip, from memory and from the store. New blocks are
+    // still validated against the UTXO set, but reorganizations can no longer
+    // reach below the pruned height.
+    pub fn prune(&mut self, keep_depth: u32) -> LuxResult<()> {
+        let pruned_height = self.store.prune(keep_depth)?;
+        for hash in &self.heights[self.pruned_height as usize..pruned_height as usize] {
+            let entry = self
+                .entries
+                .get_mut(hash)
+                .expect("canonical blocks are known");
+            entry.block = Block::new(entry.block.header().clone(), vec![]);
+        }
+        self.pruned_height = pruned_height;
+        self.forget_final_undo();
+        Ok(())
+    }
+
+    fn is_pruned(&self, entry: &ChainEntry) -> bool {
+        entry.height < self.pruned_height && self.is_canonical(entry.block.id())
+    }
+}
+
+// Fails if `params` checkpoints a different block at `height`
+fn validate_checkpoint(params: &ConsensusParams, height: u32, hash: &BlockHash) -> LuxResult<()> {
+    match params.checkpoint(height) {
+        Some(expected) if expected != hash => Err(LuxError::CheckpointMismatch {
+            height,
+            expected: *expected,
+            actual: *hash,
+        }),
+        _ => Ok(()),
+    }
+}
+
+// Rejects blocks including a transaction whose locktime hasn't passed yet
+fn validate_finality(block: &Block, height: u32) -> LuxResult<()> {
+    let time = block.header().timestamp();
+    match block
+        .transactions()
+        .iter()
+        .find(|transaction| !transaction.is_final(height, time))
+    {
+        Some(transaction) => Err(LuxError::InvalidTransaction(format!(
+            "Transaction: {} is locked until: {} but was included at height: {}.",
+            transaction.id(),
+            transaction.locktime(),
+            height
+        ))),
+        None => Ok(()),
+    }
+}
+
+// The coinbase commits to the height its block connects at, which is what
+// keeps otherwise identical coinbases at different heights from sharing an id
+fn validate_coinbase_height(block: &Block, height: u32) -> LuxResult<()> {
+    match block.height() {
+        Some(embedded) if embedded == height => Ok(()),
+        embedded => Err(LuxError::InvalidCoinbase(format!(
+            "Block: {} connects at height: {} but its coinbase commits to: {:?}.",
+            block.id(),
+            height,
+            embedded
+        ))),
+    }
+}
+
+// Checks that every transaction of the block is signed by the owners of the
+// outputs it spends and doesn't spend more than them, and that the coinbase
+// claims at most the subsidy for `height` plus the collected fees. Inputs may
+// come from `utxos` or from earlier transactions of the same block.
+fn validate_amounts(
+    utxos: &UtxoSet,
+    block: &Block,
+    height: u32,
+    params: &ConsensusParams,
+) -> LuxResult<()> {
+    let mut created = HashMap::<OutPoint, &TransactionOutput>::new();
+    let mut fees = Luxcoin::ZERO;
+    for transaction in block.transactions() {
+        let lookup = |utxo_id: &TransactionId, index: &OutputIndex| {
+            utxos
+                .get(utxo_id, index)
+                .or_else(|| created.get(&(*utxo_id, *index)).copied())
+        };
+        let fee = transaction.validate_amounts_with(|utxo_id, index| {
+            lookup(utxo_id, index).map(TransactionOutput::amount)
+        })?;
+        transaction.verify_inputs_with(lookup)?;
+        fees = fees.checked_add(fee).ok_or_else(|| {
+            LuxError::InvalidBlock(format!("Block: {} fees overflow.", block.id()))
+        })?;
+
+        for (i, output) in transaction.outputs().iter().enumerate() {
+            if !output.is_spendable() {
+                continue;
+            }
+            created.insert((*transaction.id(), OutputIndex::new(i as i32)), output);
+        }
+    }
+
+    let allowed = economics::block_subsidy(height, params)
+        .checked_add(fees)
+        .ok_or_else(|| {
+            LuxError::InvalidBlock(format!("Block: {} reward overflows.", block.id()))
+        })?;
+    match block.transactions().first() {
+        Some(coinbase) => coinbase.validate_coinbase_amount(allowed),
+        None => Err(LuxError::InvalidBlock(format!(
+            "Block: {} has no transactions.",
+            block.id()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use primitive_types::U256;
+
+    use super::{block_work, target_work, Blockchain, MAX_ORPHAN_BLOCKS};
+    use crate::core::{
+        address::Address,
+        block::{Block, BlockHash, GENESIS_TIMESTAMP},
+        consensus::ConsensusParams,
+        crypto::{self, Sha256Algorithm},
+        difficulty,
+        error::LuxError,
+        fixtures,
+        luxcoin::Luxcoin,
+        store::BlockStore,
+        timestamp::Timestamp,
+        transaction::{OutputIndex, Transaction, TransactionInput, TransactionOutput},
+    };
+
+    fn genesis() -> Block {
+        Block::genesis(
+            fixtures::address("genesis"),
+            Luxcoin::new(50),
+            0,
+            &ConsensusParams::mainnet(),
+        )
+        .unwrap()
+    }
+
+    fn coinbase(to: &str, height: u32) -> Transaction {
+        Transaction::new(
+            vec![TransactionInput::new_coinbase(height)],
+            vec![TransactionOutput::new(Address::new(to.to_string()), Luxcoin::new(50)).unwrap()],
+            0,
+        )
+        .unwrap()
+    }
+
+    // Every block gets a later timestamp than the ones built before it, so it
+    // always comes after the median time past of its ancestors
+    fn next_timestamp() -> Timestamp {
+        static OFFSET: AtomicU32 = AtomicU32::new(1);
+        GENESIS_TIMESTAMP
+            .checked_add_secs(OFFSET.fetch_add(1, Ordering::Relaxed))
+            .unwrap()
+    }
+
+    fn mined_block(previous: &BlockHash, transactions: Vec<Transaction>, difficulty: u32) -> Block {
+        fixtures::mine_block(previous, transactions, difficulty, next_timestamp())
+    }
+
+    fn block(previous: &BlockHash, height: u32, to: &str) -> Block {
+        mined_block(previous, vec![coinbase(to, height)], 0)
+    }
+
+    #[test]
+    fn append_extends_tip() {
+        let genesis = genesis();
+        let mut chain = Blockchain::new(genesis.clone(), ConsensusParams::mainnet()).unwrap();
+        assert_eq!(chain.height(), 0);
+        assert_eq!(chain.tip().id(), genesis.id());
+
+        let next = block(genesis.id(), 1, "alice");
+        chain.append(next.clone()).unwrap();
+        assert_eq!(chain.height(), 1);
+        assert_eq!(chain.tip().id(), next.id());
+        assert!(chain.get_block(genesis.id()).unwrap().is_some());
+        assert_eq!(chain.get_block_at(1).unwrap().unwrap().id(), next.id());
+        assert!(chain.get_block_at(2).unwrap().is_none());
+        assert_eq!(chain.work(), U256::from(2));
+        assert_eq!(chain.chain_work(genesis.id()), Some(U256::one()));
+        assert_eq!(chain.chain_work(next.id()), Some(chain.work()));
+        assert_eq!(chain.utxos().len(), 2);
+    }
+
+    #[test]
+    fn open_replays_stored_chain() {
+        let genesis = genesis();
+        let mut chain = Blockchain::new(genesis.clone(), ConsensusParams::mainnet()).unwrap();
+        let first = block(genesis.id(), 1, "alice");
+        let second = block(first.id(), 2, "bob");
+        let fork = block(genesis.id(), 1, "carol");
+        chain.append(first).unwrap();
+        chain.append(second.clone()).unwrap();
+        chain.append(fork).unwrap();
+        assert_eq!(chain.store().tip().unwrap(), Some(*second.id()));
+
+        let reopened = Blockchain::open(
+            chain.store().clone(),
+            genesis.clone(),
+            ConsensusParams::mainnet(),
+        )
+        .unwrap();
+        assert_eq!(reopened.height(), 2);
+        assert_eq!(reopened.tip().id(), second.id());
+        assert_eq!(reopened.utxos().len(), chain.utxos().len());
+
+        let other_genesis = Block::genesis(
+            Address::new("other".to_string()),
+            Luxcoin::new(50),
+            0,
+            &ConsensusParams::mainnet(),
+        )
+        .unwrap();
+        assert!(matches!(
+            Blockchain::open(
+                chain.store().clone(),
+                other_genesis,
+                ConsensusParams::mainnet()
+            ),
+            Err(LuxError::Storage(_))
+        ));
+    }
+
+    #[test]
+    fn append_rejects_blocks_before_median_time_past() {
+        let genesis = genesis();
+        let mut chain = Blockchain::new(genesis.clone(), ConsensusParams::mainnet()).unwrap();
+        let mut previous = *genesis.id();
+        for i in 1..=4 {
+            let at = GENESIS_TIMESTAMP.checked_add_secs(i * 10).unwrap();
+            let block = fixtures::mine_block(&previous, vec![coinbase(&i.to_string(), i)], 0, at);
+            previous = *block.id();
+            chain.append(block).unwrap();
+        }
+        // Timestamps 0, 10, 20, 30 and 40 past genesis
+        assert_eq!(
+            chain.median_time_past(5),
+            GENESIS_TIMESTAMP.checked_add_secs(20).unwrap()
+        );
+        assert_eq!(
+            chain.median_time_past(2),
+            GENESIS_TIMESTAMP.checked_add_secs(40).unwrap()
+        );
+        assert_eq!(chain.median_time_past(100), chain.median_time_past(5));
+
+        // Earlier than the tip, but still after the median
+        let at = GENESIS_TIMESTAMP.checked_add_secs(21).unwrap();
+        let late = fixtures::mine_block(&previous, vec![coinbase("late", 5)], 0, at);
+        let at = GENESIS_TIMESTAMP.checked_add_secs(20).unwrap();
+        let backdated = fixtures::mine_block(&previous, vec![coinbase("backdated", 5)], 0, at);
+
+        assert!(matches!(
+            chain.append(backdated),
+            Err(LuxError::InvalidTimestamp(_))
+        ));
+        assert!(chain.append(late).is_ok());
+    }
+
+    #[test]
+    fn append_rejects_duplicate_blocks() {
+        let genesis = genesis();
+        let mut chain = Blockchain::new(genesis.clone(), ConsensusParams::mainnet()).unwrap();
+        let next = block(genesis.id(), 1, "alice");
+        chain.append(next.clone()).unwrap();
+
+        let orphan = block(&BlockHash::new(crypto::hash(b"unknown")), 2, "bob");
+        chain.append(orphan.clone()).unwrap();
+        assert!(chain.get_block(orphan.id()).unwrap().is_none());
+        assert!(matches!(
+            chain.append(orphan),
+            Err(LuxError::InvalidBlock(_))
+        ));
+        assert!(matches!(chain.append(next), Err(LuxError::InvalidBlock(_))));
+        assert_eq!(chain.height(), 1);
+    }
+
+    #[test]
+    fn coinbase_must_commit_to_its_height() {
+        let genesis = genesis();
+        let params = ConsensusParams::mainnet();
+        let mut chain = Blockchain::new(genesis.clone(), params.clone()).unwrap();
+
+        // Would share its coinbase id with any other height 0 reward to alice
+        let stale = block(genesis.id(), 0, "alice");
+        assert!(matches!(
+            chain.append(stale),
+            Err(LuxError::InvalidCoinbase(_))
+        ));
+        let first = block(genesis.id(), 1, "alice");
+        chain.append(first.clone()).unwrap();
+        assert!(matches!(
+            chain.append(block(first.id(), 1, "alice")),
+            Err(LuxError::InvalidCoinbase(_))
+        ));
+        assert_eq!(chain.height(), 1);
+        assert_eq!(chain.tip().height(), Some(1));
+        chain.verify_all(&params).unwrap();
+    }
+
+    #[test]
+    fn orphans_connect_once_their_parent_arrives() {
+        let genesis = genesis();
+        let mut chain = Blockchain::new(genesis.clone(), ConsensusParams::mainnet()).unwrap();
+        let first = block(genesis.id(), 1, "alice");
+        let second = block(first.id(), 2, "bob");
+        let third = block(second.id(), 3, "carol");
+
+        chain.append(third.clone()).unwrap();
+        chain.append(second).unwrap();
+        assert_eq!(chain.orphan_count(), 2);
+        assert_eq!(chain.height(), 0);
+
+        chain.append(first).unwrap();
+        assert_eq!(chain.orphan_count(), 0);
+        assert_eq!(chain.height(), 3);
+        assert_eq!(chain.tip().id(), third.id());
+    }
+
+    #[test]
+    fn orphan_pool_is_bounded() {
+        let genesis = genesis();
+        let mut chain = Blockchain::new(genesis, ConsensusParams::mainnet()).unwrap();
+        for i in 0..MAX_ORPHAN_BLOCKS as u32 + 10 {
+            let unknown = BlockHash::new(crypto::hash(&i.to_le_bytes()));
+            chain.append(block(&unknown, 1, "alice")).unwrap();
+        }
+
+        assert_eq!(chain.orphan_count(), MAX_ORPHAN_BLOCKS);
+    }
+
+    #[test]
+    fn new_rejects_non_genesis() {
+        let genesis = genesis();
+        assert!(matches!(
+            Blockchain::new(block(genesis.id(), 1, "alice"), ConsensusParams::mainnet()),
+            Err(LuxError::InvalidBlock(_))
+        ));
+    }
+
+    #[test]
+    fn genesis_is_mined_with_pow_algorithm() {
+        let params = ConsensusParams {
+            pow_algorithm: &Sha256Algorithm,
+            ..ConsensusParams::mainnet()
+        };
+        let miner = Address::new("genesis".to_string());
+        let genesis = Block::genesis(miner, Luxcoin::new(50), 8, &params).unwrap();
+
+        assert!(Blockchain::new(genesis, params).is_ok());
+    }
+
+    #[test]
+    fn blocks_must_declare_the_expected_difficulty() {
+        let params = ConsensusParams {
+            difficulty_window: 2,
+            ..ConsensusParams::mainnet()
+        };
+        let miner = Address::new("genesis".to_string());
+        let genesis = Block::genesis(miner, Luxcoin::new(50), 4, &params).unwrap();
+        let mut chain = Blockchain::new(genesis.clone(), params.clone()).unwrap();
+
+        // Proof of work alone would accept the easier block
+        let easy = mined_block(genesis.id(), vec![coinbase("easy", 1)], 0);
+        assert!(matches!(
+            chain.append(easy),
+            Err(LuxError::DifficultyMismatch {
+                height: 1,
+                expected: 4,
+                actual: 0
+            })
+        ));
+        // Mined right after genesis, so the window took far less than the
+        // target spacing
+        let soon = GENESIS_TIMESTAMP.checked_add_secs(1).unwrap();
+        let first = fixtures::mine_block(genesis.id(), vec![coinbase("first", 1)], 4, soon);
+        chain.append(first.clone()).unwrap();
+
+        // The second block starts a new window, retargeted from the first one
+        let window = [genesis.header().clone(), first.header().clone()];
+        let retargeted = difficulty::retarget(&window, &params);
+        assert!(retargeted > 4);
+        let stale = mined_block(first.id(), vec![coinbase("stale", 2)], 4);
+        assert!(matches!(
+            chain.append(stale),
+            Err(LuxError::DifficultyMismatch { height: 2, .. })
+        ));
+        let second = mined_block(first.id(), vec![coinbase("second", 2)], retargeted);
+        chain.append(second.clone()).unwrap();
+        assert_eq!(chain.tip().id(), second.id());
+    }
+
+    #[test]
+    fn block_work_doubles_per_bit() {
+        assert_eq!(block_work(0).unwrap(), U256::one());
+        assert_eq!(block_work(4).unwrap(), U256::from(16));
+        assert_eq!(block_work(200).unwrap(), U256::one() << 200);
+        assert_eq!(block_work(256).unwrap(), U256::MAX);
+        assert!(block_work(257).is_err());
+    }
+
+    #[test]
+    fn target_work_rounds_down() {
+        // Met by just over a third of all hashes, so slightly less than 3
+        let target = [0x55; 32];
+        assert_eq!(
+            target_work(&BlockHash::new(crypto::Sha256::new(target))),
+            U256::from(2)
+        );
+    }
+
+    #[test]
+    fn block_locator_spacing_and_fork_point() {
+        let genesis = genesis();
+        let mut chain = Blockchain::new(genesis.clone(), ConsensusParams::mainnet()).unwrap();
+        let mut fork = Blockchain::new(genesis, ConsensusParams::mainnet()).unwrap();
+        for height in 1..=300 {
+            let next = block(chain.tip().id(), height, &format!("miner {}", height));
+            chain.append(next.clone()).unwrap();
+            if height <= 100 {
+                fork.append(next).unwrap();
+            }
+        }
+        for height in 1..=5 {
+            fork.append(block(
+                fork.tip().id(),
+                100 + height,
+                &format!("fork {}", height),
+            ))
+            .unwrap();
+        }
+
+        let heights = chain
+            .block_locator()
+            .iter()
+            .map(|hash| {
+                (0..=chain.height())
+                    .find(|height| chain.get_block_at(*height).unwrap().unwrap().id() == hash)
+                    .unwrap()
+            })
+            .collect::<Vec<u32>>();
+        let mut expected = (291..=300).rev().collect::<Vec<u32>>();
+        expected.extend([289, 285, 277, 261, 229, 165, 37, 0]);
+        assert_eq!(heights, expected);
+
+        assert_eq!(
+            chain.find_fork_point(&fork.block_locator()),
+            Some(*chain.get_block_at(100).unwrap().unwrap().id())
+        );
+        assert_eq!(chain.find_fork_point(&[*fork.tip().id()]), None);
+    }
+
+    #[test]
+    fn verify_all_reports_broken_links() {
+        let genesis = genesis();
+        let params = ConsensusParams::mainnet();
+        let mut chain = Blockchain::new(genesis.clone(), params.clone()).unwrap();
+        let first = block(genesis.id(), 1, "alice");
+        let second = block(first.id(), 2, "bob");
+        let fork = block(genesis.id(), 1, "carol");
+        for block in [first, second, fork.clone()] {
+            chain.append(block).unwrap();
+        }
+        chain.verify_all(&params).unwrap();
+
+        chain.heights[1] = *fork.id();
+        assert!(matches!(
+            chain.verify_all(&params),
+            Err(LuxError::ChainVerification { height: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn pruning_keeps_headers_and_rejects_deep_forks() {
+        let genesis = genesis();
+        let params = ConsensusParams::mainnet();
+        let mut chain = Blockchain::new(genesis.clone(), params.clone()).unwrap();
+        let mut previous = *genesis.id();
+        for i in 0..4 {
+            let block = block(&previous, i + 1, &format!("miner{}", i));
+            previous = *block.id();
+            chain.append(block).unwrap();
+        }
+
+        chain.prune(2).unwrap();
+        assert_eq!(chain.pruned_height(), 2);
+        assert!(!chain.utxos().can_revert(genesis.id()));
+        assert!(!chain
+            .utxos()
+            .can_revert(&chain.get_header_at(1).unwrap().hash()));
+        assert!(chain
+            .utxos()
+            .can_revert(&chain.get_header_at(2).unwrap().hash()));
+        assert!(matches!(
+            chain.get_block(genesis.id()),
+            Err(LuxError::BlockPruned(hash)) if &hash == genesis.id()
+        ));
+        assert!(matches!(
+            chain.get_block_at(1),
+            Err(LuxError::BlockPruned(_))
+        ));
+        assert!(chain.get_block_at(2).unwrap().is_some());
+        assert_eq!(chain.get_header_at(0).unwrap().hash(), *genesis.id());
+        chain.verify_all(&params).unwrap();
+
+        // Outgrows the chain, but replacing it would revert pruned blocks
+        let mut fork = *genesis.id();
+        for i in 0..4 {
+            let block = block(&fork, i + 1, &format!("fork{}", i));
+            fork = *block.id();
+            chain.append(block).unwrap();
+        }
+        assert!(matches!(
+            chain.append(block(&fork, 5, "fork4")),
+            Err(LuxError::InvalidBlock(_))
+        ));
+        assert_eq!(chain.tip().id(), &previous);
+        assert_eq!(chain.tips().len(), 1);
+
+        // Blocks above the pruned height can still be reorganized away
+        let shallow = block(&chain.get_header_at(3).unwrap().hash(), 4, "b4");
+        let deeper = block(shallow.id(), 5, "b5");
+        chain.append(shallow).unwrap();
+        chain.append(deeper.clone()).unwrap();
+        assert_eq!(chain.tip().id(), deeper.id());
+    }
+
+    #[test]
+    fn checkpoints_pin_the_chain() {
+        let genesis = genesis();
+        let first = block(genesis.id(), 1, "alice");
+        let second = block(first.id(), 2, "bob");
+        let params = ConsensusParams {
+            checkpoints: vec![(2, *second.id())],
+            ..ConsensusParams::mainnet()
+        };
+        let mut chain = Blockchain::new(genesis.clone(), params.clone()).unwrap();
+        chain.append(first.clone()).unwrap();
+        assert_eq!(chain.last_checkpoint(), None);
+
+        let impostor = block(first.id(), 2, "mallory");
+        assert!(matches!(
+            chain.append(impostor),
+            Err(LuxError::CheckpointMismatch { height: 2, .. })
+        ));
+        chain.append(second.clone()).unwrap();
+        assert_eq!(chain.last_checkpoint(), Some((2, *second.id())));
+        assert!(!chain.utxos().can_revert(second.id()));
+        chain.verify_all(&params).unwrap();
+
+        // Forks below the checkpoint can't rewrite history, however long
+        let fork = block(genesis.id(), 1, "fork");
+        assert!(matches!(chain.append(fork), Err(LuxError::InvalidBlock(_))));
+        assert_eq!(chain.tip().id(), second.id());
+
+        let other = ConsensusParams {
+            checkpoints: vec![(1, *second.id())],
+            ..ConsensusParams::mainnet()
+        };
+        assert!(matches!(
+            chain.verify_all(&other),
+            Err(LuxError::ChainVerification { height: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn heavier_branch_wins() {
+        let genesis = genesis();
+        let mut chain = Blockchain::new(genesis.clone(), ConsensusParams::mainnet()).unwrap();
+
+        let a1_reward = coinbase("a1", 1);
+        let a1 = mined_block(genesis.id(), vec![a1_reward.clone()], 0);
+        let a2 = block(a1.id(), 2, "a2");
+        chain.append(a1.clone()).unwrap();
+        chain.append(a2.clone()).unwrap();
+        assert_eq!(chain.tip().id(), a2.id());
+        assert_eq!(chain.height(), 2);
+
+        // A branch that only catches up, ties keep the current tip
+        let b1 = block(genesis.id(), 1, "b1");
+        let b2 = block(b1.id(), 2, "b2");
+        chain.append(b1.clone()).unwrap();
+        chain.append(b2.clone()).unwrap();
+        assert_eq!(chain.tip().id(), a2.id());
+        assert_eq!(chain.tips().len(), 2);
+
+        // One more block tips the balance
+        let b3 = block(b2.id(), 3, "b3");
+        chain.append(b3.clone()).unwrap();
+        assert_eq!(chain.tip().id(), b3.id());
+        assert_eq!(chain.height(), 3);
+        assert_eq!(chain.work(), U256::from(4));
+        assert_eq!(chain.get_block_at(1).unwrap().unwrap().id(), b1.id());
+        assert!(chain.get_block(a2.id()).unwrap().is_some());
+
+        let utxos = chain.utxos();
+        assert!(!utxos.contains(a1_reward.id(), &OutputIndex::new(0)));
+        assert!(utxos.contains(b3.transactions()[0].id(), &OutputIndex::new(0)));
+        assert_eq!(utxos.len(), 4);
+    }
+
+    #[test]
+    fn coinbase_may_claim_subsidy_and_fees() {
+        let genesis = genesis();
+        let genesis_reward = genesis.transactions()[0].clone();
+        let mut chain = Blockchain::new(genesis.clone(), ConsensusParams::mainnet()).unwrap();
+
+        let payment = |amount| {
+            let transaction = Transaction::new(
+                vec![TransactionInput::new(
+                    *genesis_reward.id(),
+                    OutputIndex::new(0),
+                )],
+                vec![
+                    TransactionOutput::new(Address::new("bob".to_string()), Luxcoin::new(amount))
+                        .unwrap(),
+                ],
+                0,
+            )
+            .unwrap();
+            fixtures::sign(transaction, "genesis", &[&genesis_reward.outputs()[0]])
+        };
+        let reward = |amount| {
+            Transaction::new_coinbase(
+                Address::new("miner".to_string()),
+                ConsensusParams::mainnet().initial_subsidy,
+                Luxcoin::new(amount),
+                1,
+            )
+            .unwrap()
+        };
+
+        let greedy = mined_block(genesis.id(), vec![reward(11), payment(40)], 0);
+        assert!(matches!(
+            chain.append(greedy),
+            Err(LuxError::InvalidTransaction(_))
+        ));
+        let overspent = mined_block(genesis.id(), vec![reward(0), payment(60)], 0);
+        assert!(matches!(
+            chain.append(overspent),
+            Err(LuxError::InsufficientFunds { .. })
+        ));
+        assert_eq!(chain.height(), 0);
+
+        let fair = mined_block(genesis.id(), vec![reward(10), payment(40)], 0);
+        chain.append(fair.clone()).unwrap();
+        assert_eq!(chain.tip().id(), fair.id());
+    }
+
+    #[test]
+    fn spends_need_the_owners_signature() {
+        let genesis = genesis();
+        let genesis_reward = genesis.transactions()[0].clone();
+        let mut chain = Blockchain::new(genesis.clone(), ConsensusParams::mainnet()).unwrap();
+
+        let payment = Transaction::new(
+            vec![TransactionInput::new(
+                *genesis_reward.id(),
+                OutputIndex::new(0),
+            )],
+            vec![TransactionOutput::new(fixtures::address("bob"), Luxcoin::new(50)).unwrap()],
+            0,
+        )
+        .unwrap();
+        let unsigned = mined_block(genesis.id(), vec![coinbase("a1", 1), payment.clone()], 0);
+        assert!(matches!(
+            chain.append(unsigned),
+            Err(LuxError::InvalidSignature { .. })
+        ));
+
+        let spent = &genesis_reward.outputs()[0];
+        let forged = fixtures::sign(payment.clone(), "mallory", &[spent]);
+        let forged = mined_block(genesis.id(), vec![coinbase("a1", 1), forged], 0);
+        assert!(matches!(
+            chain.append(forged),
+            Err(LuxError::InvalidSignature { .. })
+        ));
+        assert_eq!(chain.height(), 0);
+
+        let signed = fixtures::sign(payment, "genesis", &[spent]);
+        chain
+            .append(mined_block(
+                genesis.id(),
+                vec![coinbase("a1", 1), signed],
+                0,
+            ))
+            .unwrap();
+        assert_eq!(chain.height(), 1);
+    }
+
+    #[test]
+    fn rejects_non_final_transactions() {
+        let genesis = genesis();
+        let genesis_reward = genesis.transactions()[0].clone();
+        let mut chain = Blockchain::new(genesis.clone(), ConsensusParams::mainnet()).unwrap();
+
+        let locked = |locktime| {
+            let transaction = Transaction::new(
+                vec![TransactionInput::new(
+                    *genesis_reward.id(),
+                    OutputIndex::new(0),
+                )],
+                vec![
+                    TransactionOutput::new(Address::new("bob".to_string()), Luxcoin::new(50))
+                        .unwrap(),
+                ],
+                locktime,
+            )
+            .unwrap();
+            fixtures::sign(transaction, "genesis", &[&genesis_reward.outputs()[0]])
+        };
+
+        let early = mined_block(genesis.id(), vec![coinbase("a1", 1), locked(2)], 0);
+        assert!(matches!(
+            chain.append(early),
+            Err(LuxError::InvalidTransaction(_))
+        ));
+        assert_eq!(chain.height(), 0);
+
+        let on_time = mined_block(genesis.id(), vec![coinbase("a1", 1), locked(1)], 0);
+        chain.append(on_time).unwrap();
+        assert_eq!(chain.height(), 1);
+    }
+
+    #[test]
+    fn invalid_branch_is_discarded() {
+        let genesis = genesis();
+        let genesis_reward = genesis.transactions()[0].clone();
+        let mut chain = Blockchain::new(genesis.clone(), ConsensusParams::mainnet()).unwrap();
+
+        let a1 = block(genesis.id(), 1, "a1");
+        chain.append(a1.clone()).unwrap();
+
+        // Spends an output that doesn't exist, so the branch can't be applied
+        let missing = Transaction::new(
+            vec![TransactionInput::new(
+                *genesis_reward.id(),
+                OutputIndex::new(7),
+            )],
+            vec![TransactionOutput::new(Address::new("bob".to_string()), Luxcoin::new(1)).unwrap()],
+            0,
+        )
+        .unwrap();
+        let b1 = mined_block(genesis.id(), vec![coinbase("b1", 1), missing], 0);
+        chain.append(b1.clone()).unwrap();
+        let b2 = block(b1.id(), 2, "b2");
+        assert!(matches!(
+            chain.append(b2.clone()),
+            Err(LuxError::MissingOutput { .. })
+        ));
+
+        assert_eq!(chain.tip().id(), a1.id());
+        assert!(chain.get_block(b1.id()).unwrap().is_none());
+        assert!(chain.get_block(b2.id()).unwrap().is_none());
+        assert_eq!(chain.tips().len(), 1);
+        assert!(chain
+            .utxos()
+            .contains(a1.transactions()[0].id(), &OutputIndex::new(0)));
+        assert_eq!(chain.utxos().len(), 2);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::core::{luxcoin::Luxcoin, utxo::OutPoint};
+
+// Picks which unspent outputs fund a transaction
+pub trait CoinSelection {
+    // Returns outputs from `candidates` whose amounts add up to at least
+    // `target`, or None if that isn't possible
+    fn select(&self, candidates: &[(OutPoint, Luxcoin)], target: Luxcoin) -> Option<Vec<OutPoint>>;
+}
+
+// Spends the biggest outputs first, keeping the number of inputs low
+pub struct LargestFirst;
+
+impl CoinSelection for LargestFirst {
+    fn select(&self, candidates: &[(OutPoint, Luxcoin)], target: Luxcoin) -> Option<Vec<OutPoint>> {
+        let mut sorted = candidates.to_vec();
+        sorted.sort_by(|(_, lhs), (_, rhs)| rhs.cmp(lhs));
+        accumulate(&sorted, target)
+    }
+}
+
+// Spends the smallest outputs first, consolidating dust at the cost of more
+// inputs
+pub struct SmallestFirst;
+
+impl CoinSelection for SmallestFirst {
+    fn select(&self, candidates: &[(OutPoint, Luxcoin)], target: Luxcoin) -> Option<Vec<OutPoint>> {
+        let mut sorted = candidates.to_vec();
+        sorted.sort_by_key(|(_, amount)| *amount);
+        accumulate(&sorted, target)
+    }
+}
+
+// Depth first search for a set of outputs adding up to exactly the target, so
+// the transaction needs no change output. Gives up after `max_tries` steps and
+// falls back to `LargestFirst`.
+pub struct BranchAndBound {
+    pub max_tries: usize,
+}
+
+impl Default for BranchAndBound {
+    fn default() -> Self {
+        Self { max_tries: 100_000 }
+    }
+}
+
+impl CoinSelection for BranchAndBound {
+    fn select(&self, candidates: &[(OutPoint, Luxcoin)], target: Luxcoin) -> Option<Vec<OutPoint>> {
+        let mut sorted = candidates.to_vec();
+        sorted.sort_by(|(_, lhs), (_, rhs)| rhs.cmp(lhs));
+
+        let amounts = sorted
+            .iter()
+            .map(|(_, amount)| amount.as_i64() as i128)
+            .collect::<Vec<i128>>();
+        // remaining[i] is the sum of every amount from i onwards
+        let mut remaining = vec![0; amounts.len() + 1];
+        for i in (0..amounts.len()).rev() {
+            remaining[i] = remaining[i + 1] + amounts[i];
+        }
+
+        let mut search = Search {
+            amounts: &amounts,
+            remaining: &remaining,
+            target: target.as_i64() as i128,
+            tries: self.max_tries,
+            chosen: vec![],
+        };
+        if search.run(0, 0) {
+            Some(search.chosen.iter().map(|&i| sorted[i].0).collect())
+        } else {
+            LargestFirst.select(candidates, target)
+        }
+    }
+}
+
+struct Search<'a> {
+    amounts: &'a [i128],
+    remaining: &'a [i128],
+    target: i128,
+    tries: usize,
+    chosen: Vec<usize>,
+}
+
+impl Search<'_> {
+    // Includes or skips the amount at `index`, pruning branches that overshoot
+    // or can no longer reach the target
+    fn run(&mut self, index: usize, total: i128) -> bool {
+        if total == self.target {
+            return true;
+        }
+        if total > self.target
+            || index == self.amounts.len()
+            || total + self.remaining[index] < self.target
+            || self.tries == 0
+        {
+            return false;
+        }
+        self.tries -= 1;
+
+        self.chosen.push(index);
+        if self.run(index + 1, total + self.amounts[index]) {
+            return true;
+        }
+        self.chosen.pop();
+        self.run(index + 1, total)
+    }
+}
+
+// Takes candidates in order until they cover the target
+fn accumulate(sorted: &[(OutPoint, Luxcoin)], target: Luxcoin) -> Option<Vec<OutPoint>> {
+    let mut selected = vec![];
+    let mut total = 0i128;
+    for (outpoint, amount) in sorted {
+        if total >= target.as_i64() as i128 {
+            break;
+        }
+        selected.push(*outpoint);
+        total += amount.as_i64() as i128;
+    }
+
+    (total >= target.as_i64() as i128).then_some(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BranchAndBound, CoinSelection, LargestFirst, SmallestFirst};
+    use crate::core::{
+        crypto,
+        luxcoin::Luxcoin,
+        transaction::{OutputIndex, TransactionId},
+        utxo::OutPoint,
+    };
+
+    fn outpoint(index: i32) -> OutPoint {
+        (
+            TransactionId::new(crypto::hash(b"funding")),
+            OutputIndex::new(index),
+        )
+    }
+
+    // Outputs 0 to 3 worth 10, 60, 30 and 25
+    fn candidates() -> Vec<(OutPoint, Luxcoin)> {
+        [10, 60, 30, 25]
+            .iter()
+            .enumerate()
+            .map(|(i, amount)| (outpoint(i as i32), Luxcoin::new(*amount)))
+            .collect()
+    }
+
+    fn select<S: CoinSelection>(selector: S, target: i64) -> Option<Vec<OutPoint>> {
+        selector.select(&candidates(), Luxcoin::new(target))
+    }
+
+    #[test]
+    fn largest_first() {
+        assert_eq!(select(LargestFirst, 40), Some(vec![outpoint(1)]));
+        assert_eq!(
+            select(LargestFirst, 70),
+            Some(vec![outpoint(1), outpoint(2)])
+        );
+        assert_eq!(select(LargestFirst, 126), None);
+    }
+
+    #[test]
+    fn smallest_first() {
+        assert_eq!(
+            select(SmallestFirst, 40),
+            Some(vec![outpoint(0), outpoint(3), outpoint(2)])
+        );
+        assert_eq!(select(SmallestFirst, 10), Some(vec![outpoint(0)]));
+        assert_eq!(select(SmallestFirst, 126), None);
+    }
+
+    #[test]
+    fn branch_and_bound_finds_exact_match() {
+        assert_eq!(
+            select(BranchAndBound::default(), 40),
+            Some(vec![outpoint(2), outpoint(0)])
+        );
+        assert_eq!(
+            select(BranchAndBound::default(), 95),
+            Some(vec![outpoint(1), outpoint(3), outpoint(0)])
+        );
+        assert_eq!(
+            select(BranchAndBound::default(), 125),
+            Some(vec![outpoint(1), outpoint(2), outpoint(3), outpoint(0)])
+        );
+    }
+
+    #[test]
+    fn branch_and_bound_falls_back_to_largest_first() {
+        assert_eq!(
+            select(BranchAndBound::default(), 41),
+            select(LargestFirst, 41)
+        );
+        assert_eq!(
+            select(BranchAndBound { max_tries: 0 }, 40),
+            Some(vec![outpoint(1)])
+        );
+        assert_eq!(select(BranchAndBound::default(), 126), None);
+    }
+}
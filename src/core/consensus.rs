@@ -0,0 +1,91 @@
+use crate::core::{
+    block::BlockHash,
+    crypto::{DoubleSha256Algorithm, HashAlgorithm},
+    luxcoin::{Luxcoin, UNITS_PER_LUX},
+};
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    // Version byte prefixed to the public key hash of the network's addresses
+    pub fn address_version(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x30,
+            Network::Testnet => 0x6f,
+        }
+    }
+
+    pub fn from_address_version(version: u8) -> Option<Self> {
+        [Network::Mainnet, Network::Testnet]
+            .into_iter()
+            .find(|network| network.address_version() == version)
+    }
+}
+
+// Rules every node on a network must agree on
+#[derive(Clone, Debug)]
+pub struct ConsensusParams {
+    pub network: Network,
+    // Coinbase reward of the first halving period
+    pub initial_subsidy: Luxcoin,
+    // Number of blocks between two halvings of the subsidy
+    pub halving_interval: u32,
+    // Time the network aims to spend mining each block
+    pub target_spacing_secs: u32,
+    // Number of trailing headers difficulty retargeting looks at
+    pub difficulty_window: usize,
+    // Largest accepted block, in bytes of its binary encoding
+    pub max_block_size: usize,
+    // Largest accepted block weight, see `Transaction::weight`
+    pub max_block_weight: u64,
+    // Hash a header must meet the target with. Block ids are always the double
+    // SHA-256 of the header, whatever the proof of work hash.
+    pub pow_algorithm: &'static dyn HashAlgorithm,
+    // Block hashes the chain must have at the given heights. History below
+    // the last one reached is settled, so no fork may start under it.
+    pub checkpoints: Vec<(u32, BlockHash)>,
+}
+
+impl ConsensusParams {
+    pub fn mainnet() -> Self {
+        Self {
+            network: Network::Mainnet,
+            initial_subsidy: Luxcoin::new(50 * UNITS_PER_LUX),
+            halving_interval: 210_000,
+            target_spacing_secs: 600,
+            difficulty_window: 2016,
+            max_block_size: 1_000_000,
+            max_block_weight: 4_000_000,
+            pow_algorithm: &DoubleSha256Algorithm,
+            checkpoints: vec![],
+        }
+    }
+
+    // Same monetary policy as mainnet on a much shorter schedule, with faster
+    // blocks and quicker difficulty adjustments
+    pub fn testnet() -> Self {
+        Self {
+            network: Network::Testnet,
+            initial_subsidy: Luxcoin::new(50 * UNITS_PER_LUX),
+            halving_interval: 150,
+            target_spacing_secs: 60,
+            difficulty_window: 20,
+            max_block_size: 1_000_000,
+            max_block_weight: 4_000_000,
+            pow_algorithm: &DoubleSha256Algorithm,
+            checkpoints: vec![],
+        }
+    }
+
+    // The hash checkpointed at `height`, if any
+    pub fn checkpoint(&self, height: u32) -> Option<&BlockHash> {
+        self.checkpoints
+            .iter()
+            .find(|(checkpoint, _)| *checkpoint == height)
+            .map(|(_, hash)| hash)
+    }
+}
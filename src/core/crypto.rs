@@ -1,13 +1,24 @@
-use std::fmt::{self, Display};
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
 
 use serde::{
     de::{self, Visitor},
     Deserialize, Serialize,
 };
 use sha2::Digest;
+use subtle::ConstantTimeEq;
 
-use crate::core::{block::BlockHash, transaction::Transaction};
+use crate::core::{
+    block::BlockHash,
+    difficulty::MAX_DIFFICULTY,
+    error::{LuxError, LuxResult},
+    transaction::Transaction,
+};
 
+// The derived `==` stops at the first differing byte, so its timing reveals how
+// much of a prefix matched. Use `Sha256::ct_eq` to compare against secrets.
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Sha256([u8; 32]);
 
@@ -19,6 +30,45 @@ impl Sha256 {
     pub fn as_slice(&self) -> &[u8] {
         &self.0
     }
+
+    // Compares all 32 bytes regardless of where they first differ. Prefer this
+    // over `==` whenever one side is secret, e.g. a MAC or authentication tag.
+    pub fn ct_eq(&self, other: &Sha256) -> bool {
+        self.0.ct_eq(&other.0).into()
+    }
+
+    // Counts zero bits from the most significant bit of the first byte, so a
+    // hash meets difficulty `n` exactly when this is at least `n`
+    pub fn leading_zero_bits(&self) -> u32 {
+        match self.0.iter().position(|byte| *byte != 0) {
+            Some(index) => index as u32 * 8 + self.0[index].leading_zeros(),
+            None => 256,
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for Sha256 {
+    type Error = LuxError;
+
+    fn try_from(bytes: &[u8]) -> LuxResult<Self> {
+        let bytes: [u8; 32] = bytes.try_into().map_err(|_| {
+            LuxError::HashParse(format!(
+                "Invalid sha length. Expected: {} but got: {}",
+                32,
+                bytes.len()
+            ))
+        })?;
+
+        Ok(Self(bytes))
+    }
+}
+
+impl TryFrom<Vec<u8>> for Sha256 {
+    type Error = LuxError;
+
+    fn try_from(bytes: Vec<u8>) -> LuxResult<Self> {
+        Self::try_from(bytes.as_slice())
+    }
 }
 
 impl AsRef<[u8; 32]> for Sha256 {
@@ -27,12 +77,18 @@ impl AsRef<[u8; 32]> for Sha256 {
     }
 }
 
+// Hex string in human readable formats such as JSON, the raw 32 bytes in
+// binary formats such as bincode
 impl Serialize for Sha256 {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(as_hex(self.as_ref()).as_str())
+        if serializer.is_human_readable() {
+            serializer.serialize_str(as_hex(self.as_ref()).as_str())
+        } else {
+            self.0.serialize(serializer)
+        }
     }
 }
 
@@ -41,7 +97,19 @@ impl<'de> Deserialize<'de> for Sha256 {
     where
         D: de::Deserializer<'de>,
     {
-        deserializer.deserialize_str(StringVisitor)
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(StringVisitor)
+        } else {
+            <[u8; 32]>::deserialize(deserializer).map(Self)
+        }
+    }
+}
+
+impl FromStr for Sha256 {
+    type Err = LuxError;
+
+    fn from_str(s: &str) -> LuxResult<Self> {
+        from_hex(s)
     }
 }
 
@@ -57,18 +125,23 @@ impl<'de> Visitor<'de> for StringVisitor {
     type Value = Sha256;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("test")
+        formatter.write_str("a 64-character hex-encoded SHA-256 hash")
     }
 
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        from_hex(v).map_err(|e| E::custom(e))
+        let digits = hex_digits(v);
+        if digits.len() != 64 {
+            return Err(E::invalid_length(digits.len(), &self));
+        }
+
+        from_hex(v).map_err(E::custom)
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MerkleHash(Sha256);
 
 impl MerkleHash {
@@ -97,106 +170,536 @@ pub fn as_hex(bytes: &[u8]) -> String {
     hex::encode(bytes)
 }
 
-pub fn from_hex(s: &str) -> Result<Sha256, String> {
-    match hex::decode(&s) {
-        Ok(bytes) => {
-            let mut sha = [0; 32];
-            if bytes.len() == 32 {
-                for i in 0..32 {
-                    sha[i] = *bytes.get(i).unwrap();
-                }
-                Ok(Sha256::new(sha))
-            } else {
-                Err(format!(
-                    "Invalid sha length. Expected: {} but got: {} in: {}",
-                    32,
-                    bytes.len(),
-                    s
-                ))
-            }
-        }
-        Err(e) => Err(e.to_string()),
+// Accepts surrounding whitespace, an optional `0x`/`0X` prefix and hex digits
+// in any case, as hashes pasted from logs and explorers often have them
+pub fn from_hex(s: &str) -> LuxResult<Sha256> {
+    let digits = hex_digits(s);
+    let bytes = hex::decode(digits).map_err(|e| LuxError::HashParse(format!("{} in: {}", e, s)))?;
+
+    Sha256::try_from(bytes).map_err(|e| match e {
+        LuxError::HashParse(msg) => LuxError::HashParse(format!("{} in: {}", msg, s)),
+        e => e,
+    })
+}
+
+// The hex digits of `s`, without surrounding whitespace or a `0x` prefix
+fn hex_digits(s: &str) -> &str {
+    let s = s.trim();
+    s.strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s)
+}
+
+// A hash function producing 32 bytes, so that a network can pick its proof of
+// work hash through `ConsensusParams::pow_algorithm`
+pub trait HashAlgorithm: fmt::Debug + Sync {
+    fn digest(&self, data: &[u8]) -> [u8; 32];
+
+    // Same as `digest`, wrapped in the module's 32 byte hash type
+    fn hash(&self, data: &[u8]) -> Sha256 {
+        Sha256::new(self.digest(data))
     }
 }
 
+// Single SHA-256 pass, see `hash`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Algorithm;
+
+impl HashAlgorithm for Sha256Algorithm {
+    fn digest(&self, data: &[u8]) -> [u8; 32] {
+        hash(data).0
+    }
+}
+
+// SHA-256 applied twice, see `hash256`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DoubleSha256Algorithm;
+
+impl HashAlgorithm for DoubleSha256Algorithm {
+    fn digest(&self, data: &[u8]) -> [u8; 32] {
+        hash256(data).0
+    }
+}
+
+// Incremental SHA-256, for inputs that are produced piece by piece and would
+// otherwise have to be collected into one buffer first
+#[derive(Clone, Default)]
+pub struct Sha256Hasher(sha2::Sha256);
+
+impl Sha256Hasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    pub fn finalize(self) -> Sha256 {
+        let mut output = [0; 32];
+        output.copy_from_slice(&self.0.finalize());
+        Sha256::new(output)
+    }
+
+    // Same as `hash256` over everything fed to the hasher
+    pub fn finalize256(self) -> Sha256 {
+        hash(self.finalize().as_slice())
+    }
+}
+
+// Single SHA-256 pass. Merkle leaves and interior nodes use it behind a
+// domain prefix.
 pub fn hash(data: &[u8]) -> Sha256 {
-    let mut hasher = sha2::Sha256::new();
+    let mut hasher = Sha256Hasher::new();
     hasher.update(data);
+    hasher.finalize()
+}
 
-    let result = hasher.finalize();
-    let mut output = [0; 32];
-    result
-        .iter()
-        .enumerate()
-        .for_each(|(i, byte)| output[i] = *byte);
+// Single SHA-256 pass over the concatenation of `parts`, without building it.
+// Nothing separates the parts, so callers whose parts vary in length have to
+// length prefix them.
+pub fn hash_parts(parts: &[&[u8]]) -> Sha256 {
+    let mut hasher = Sha256Hasher::new();
+    parts.iter().for_each(|part| hasher.update(part));
+    hasher.finalize()
+}
+
+// SHA-256 applied twice, SHA256(SHA256(data)), as used by Bitcoin to defend
+// against length-extension attacks. Used for block header and transaction ids.
+pub fn hash256(data: &[u8]) -> Sha256 {
+    hash(hash(data).as_slice())
+}
 
-    Sha256::new(output)
+// RIPEMD-160 of `data`. Only used through `hash160`, but exposed for checking
+// against other implementations.
+pub fn ripemd160(data: &[u8]) -> [u8; 20] {
+    let mut output = [0; 20];
+    output.copy_from_slice(&ripemd160::Ripemd160::digest(data));
+    output
 }
 
-pub fn target_hash(n_zero_bits: u32) -> BlockHash {
+// RIPEMD160(SHA256(data)), the 20 byte public key hash standard address
+// schemes encode
+pub fn hash160(data: &[u8]) -> [u8; 20] {
+    ripemd160(hash(data).as_slice())
+}
+
+// SHA-256 processes input in 64 byte blocks, which HMAC pads its key to
+const SHA256_BLOCK_SIZE: usize = 64;
+
+// HMAC-SHA256 (RFC 2104): H((K ^ opad) || H((K ^ ipad) || message)), where
+// keys longer than a block are hashed first. Compare tags with `Sha256::ct_eq`.
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> Sha256 {
+    let mut block = [0; SHA256_BLOCK_SIZE];
+    if key.len() > SHA256_BLOCK_SIZE {
+        block[..32].copy_from_slice(hash(key).as_slice());
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256Hasher::new();
+    inner.update(&block.map(|byte| byte ^ 0x36));
+    inner.update(message);
+
+    let mut outer = Sha256Hasher::new();
+    outer.update(&block.map(|byte| byte ^ 0x5c));
+    outer.update(inner.finalize().as_slice());
+    outer.finalize()
+}
+
+// The largest hash with `n_zero_bits` leading zero bits. Difficulties come
+// from untrusted headers, so anything past `MAX_DIFFICULTY` is an error rather
+// than a panic. At exactly 256 bits only the all zero hash qualifies.
+pub fn target_hash(n_zero_bits: u32) -> LuxResult<BlockHash> {
+    if n_zero_bits > MAX_DIFFICULTY {
+        return Err(LuxError::InvalidTarget(format!(
+            "Difficulty: {} is more than {} zero bits",
+            n_zero_bits, MAX_DIFFICULTY
+        )));
+    }
+
     let mut hash = [0xff; 32];
 
     let num_zero_bytes = (n_zero_bits / 8) as usize;
-    for i in 0..num_zero_bytes {
-        hash[i] = 0;
-    }
+    hash[..num_zero_bytes].fill(0);
 
     let remainder = 8 - (n_zero_bits % 8);
     if remainder == 8 {
-        return BlockHash::new(Sha256::new(hash));
+        return Ok(BlockHash::new(Sha256::new(hash)));
     }
 
     hash[num_zero_bytes] = (1 << remainder) - 1;
-    BlockHash::new(Sha256::new(hash))
+    Ok(BlockHash::new(Sha256::new(hash)))
+}
+
+// Compact "bits" encoding of a target: the high byte is the target's length
+// in bytes and the low 3 bytes hold its most significant bytes, so that
+// target = mantissa * 256^(exponent - 3). The 0x00800000 bit is a sign bit and
+// must be clear. Block headers still store difficulty as a count of leading
+// zero bits, see `target_hash`.
+pub fn bits_to_target(bits: u32) -> LuxResult<BlockHash> {
+    if bits & 0x0080_0000 != 0 {
+        return Err(LuxError::InvalidTarget(format!(
+            "Compact bits: {:#010x} encode a negative target",
+            bits
+        )));
+    }
+
+    let exponent = (bits >> 24) as isize;
+    let mut target = [0; 32];
+    for (i, byte) in (bits & 0x007f_ffff).to_be_bytes()[1..].iter().enumerate() {
+        let position = 32 - exponent + i as isize;
+        if (0..32).contains(&position) {
+            target[position as usize] = *byte;
+        } else if position < 0 && *byte != 0 {
+            return Err(LuxError::InvalidTarget(format!(
+                "Compact bits: {:#010x} overflow 256 bits",
+                bits
+            )));
+        }
+    }
+
+    let target = BlockHash::new(Sha256::new(target));
+    if target_to_bits(&target) != bits {
+        return Err(LuxError::InvalidTarget(format!(
+            "Compact bits: {:#010x} are not canonically encoded",
+            bits
+        )));
+    }
+
+    Ok(target)
 }
 
-pub struct MerkleTree(MerkleHash);
+pub fn target_to_bits(target: &BlockHash) -> u32 {
+    let bytes = target.as_slice();
+    let mut size = bytes.iter().skip_while(|byte| **byte == 0).count();
+    let start = bytes.len() - size;
+    let mut mantissa = (0..3).fold(0, |mantissa, i| {
+        (mantissa << 8) | *bytes.get(start + i).unwrap_or(&0) as u32
+    });
+
+    // Keep the sign bit clear by dropping a mantissa byte and growing the size
+    if mantissa & 0x0080_0000 != 0 {
+        mantissa >>= 8;
+        size += 1;
+    }
+
+    (size as u32) << 24 | mantissa
+}
+
+// Leaves and interior nodes are hashed behind different prefixes, so an
+// interior node can't be passed off as a leaf (a second preimage of the root)
+const MERKLE_LEAF_PREFIX: u8 = 0x00;
+const MERKLE_NODE_PREFIX: u8 = 0x01;
+
+fn hash_leaf(leaf: &[u8]) -> Sha256 {
+    hash_parts(&[&[MERKLE_LEAF_PREFIX], leaf])
+}
+
+fn hash_pair(lhs: &Sha256, rhs: &Sha256) -> Sha256 {
+    hash_parts(&[&[MERKLE_NODE_PREFIX], lhs.as_slice(), rhs.as_slice()])
+}
+
+pub struct MerkleTree {
+    // Every level of the tree, from the leaf hashes up to the root. Odd levels
+    // are stored without the duplicated trailing node.
+    levels: Vec<Vec<Sha256>>,
+    root: MerkleHash,
+    duplicate_odd: bool,
+}
 
 impl MerkleTree {
+    // Builds the tree with odd-node duplication: whenever a level has an odd
+    // number of nodes, the last one is hashed with itself. This mirrors
+    // Bitcoin and is what block Merkle roots use, but it means a list ending
+    // in a repeated node at any level (e.g. 7 leaves vs. the same 7 with the
+    // last one repeated) produces the same root (CVE-2012-2459). Use
+    // `duplicates_odd_nodes` to detect when a tree relied on this rule.
+    #[allow(clippy::ptr_arg)]
     pub fn new(leaves: &Vec<&[u8]>) -> MerkleTree {
-        let mut hashes: Vec<Sha256> = leaves.iter().map(|leaf| hash(*leaf)).collect();
-        while hashes.len() != 1 {
-            if hashes.len() % 2 == 1 {
-                hashes.push(hashes.last().unwrap().clone());
-            }
+        Self::new_with_options(leaves, true)
+    }
 
-            let mut next_level_hashes = vec![];
-            (0..hashes.len()).step_by(2).for_each(|i: usize| {
-                let lhs = hashes.get(i).unwrap();
-                let rhs = hashes.get(i + 1).unwrap();
+    // Same as `new`, but fails instead of falling back to
+    // `MerkleTree::empty_root` when there are no leaves, for callers where an
+    // empty tree can only be a mistake
+    pub fn try_new(leaves: &[&[u8]]) -> LuxResult<MerkleTree> {
+        if leaves.is_empty() {
+            return Err(LuxError::EmptyMerkleTree);
+        }
 
-                let mut concat: Vec<u8> = lhs.as_ref().iter().map(|byte: &u8| *byte).collect();
-                concat.extend_from_slice(rhs.as_ref());
-                next_level_hashes.push(hash(&concat));
-            });
+        Ok(Self::new_with_options(leaves, true))
+    }
+
+    // With `duplicate_odd` unset, an odd trailing node is promoted to the
+    // next level unchanged instead of being paired with itself, so distinct
+    // leaf lists never share a root through duplication.
+    pub fn new_with_options(leaves: &[&[u8]], duplicate_odd: bool) -> MerkleTree {
+        Self::build(
+            leaves.iter().map(|leaf| hash_leaf(leaf)).collect(),
+            duplicate_odd,
+        )
+    }
+
+    // Treats `hashes` as the already hashed leaves, e.g. transaction ids, and
+    // only combines them pairwise. They don't get the leaf prefix `new` hashes
+    // leaves with. Odd nodes are duplicated as in `new`.
+    pub fn from_hashes(hashes: Vec<Sha256>) -> MerkleTree {
+        Self::build(hashes, true)
+    }
+
+    // A single leaf is its own root. Without leaves there is nothing to
+    // combine, so the root is the hash of empty input (`MerkleTree::empty_root`)
+    // and the tree keeps a single empty level.
+    fn build(leaf_hashes: Vec<Sha256>, duplicate_odd: bool) -> MerkleTree {
+        if leaf_hashes.is_empty() {
+            return MerkleTree {
+                levels: vec![leaf_hashes],
+                root: Self::empty_root(),
+                duplicate_odd,
+            };
+        }
 
-            hashes = next_level_hashes;
+        let mut levels = vec![];
+        let mut level = leaf_hashes;
+        while level.len() > 1 {
+            let next_level = combine_level(&level, duplicate_odd);
+            levels.push(level);
+            level = next_level;
         }
 
-        MerkleTree(MerkleHash::new(hashes.into_iter().next().unwrap()))
+        // Every level halves the one below, down to the single root
+        let root = MerkleHash::new(level[0]);
+        levels.push(level);
+        MerkleTree {
+            levels,
+            root,
+            duplicate_odd,
+        }
+    }
+
+    // Root of a tree without leaves, the hash of empty input. No leaf hashes to
+    // it, since leaves are hashed behind a prefix.
+    pub fn empty_root() -> MerkleHash {
+        MerkleHash::new(hash(&[]))
+    }
+
+    // True if some level below the root had an odd number of nodes and its
+    // last node was hashed with itself
+    pub fn duplicates_odd_nodes(&self) -> bool {
+        self.duplicate_odd
+            && self.levels[..self.levels.len() - 1]
+                .iter()
+                .any(|level| level.len() % 2 == 1)
+    }
+
+    pub fn root(&self) -> &MerkleHash {
+        &self.root
+    }
+
+    pub fn leaves(&self) -> &[Sha256] {
+        &self.levels[0]
+    }
+
+    pub fn levels(&self) -> &[Vec<Sha256>] {
+        &self.levels
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    // Number of levels above the leaves, which is also how many siblings a
+    // proof holds with odd-node duplication. Duplicated nodes pair up within
+    // their level, so they don't add levels: 3 leaves are 2 deep, like 4.
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    // Builds the sibling path from the leaf at `leaf_index` up to the root
+    pub fn proof(&self, leaf_index: usize) -> Option<MerkleProof> {
+        if leaf_index >= self.levels[0].len() {
+            return None;
+        }
+
+        let mut index = leaf_index;
+        let mut siblings = vec![];
+        for level in &self.levels[..self.levels.len() - 1] {
+            if index.is_multiple_of(2) {
+                match level.get(index + 1) {
+                    Some(hash) => siblings.push((*hash, SiblingPosition::Right)),
+                    None if self.duplicate_odd => {
+                        siblings.push((level[index], SiblingPosition::Right))
+                    }
+                    // Promoted unchanged, so there is no sibling at this level
+                    None => {}
+                }
+            } else {
+                siblings.push((level[index - 1], SiblingPosition::Left));
+            }
+
+            index /= 2;
+        }
+
+        Some(MerkleProof {
+            siblings,
+            leaf_index,
+            leaf_count: self.levels[0].len(),
+            duplicate_odd: self.duplicate_odd,
+        })
     }
 }
 
 impl AsRef<MerkleHash> for MerkleTree {
     fn as_ref(&self) -> &MerkleHash {
-        &self.0
+        self.root()
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SiblingPosition {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    // Sibling hashes ordered from the leaf level up to just below the root
+    siblings: Vec<(Sha256, SiblingPosition)>,
+    // Position of the proven leaf and shape of the tree, which fix how many
+    // siblings the proof must have and on which side
+    leaf_index: usize,
+    leaf_count: usize,
+    duplicate_odd: bool,
+}
+
+impl MerkleProof {
+    pub fn siblings(&self) -> &[(Sha256, SiblingPosition)] {
+        &self.siblings
+    }
+
+    pub fn leaf_index(&self) -> usize {
+        self.leaf_index
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    pub fn verify(&self, leaf: &[u8], root: &MerkleHash) -> bool {
+        self.verify_hash(&hash_leaf(leaf), root)
+    }
+
+    // Same as `verify` for trees built with `MerkleTree::from_hashes`
+    pub fn verify_hash(&self, leaf_hash: &Sha256, root: &MerkleHash) -> bool {
+        if !self.has_expected_shape() {
+            return false;
+        }
+
+        let computed = self
+            .siblings
+            .iter()
+            .fold(*leaf_hash, |current, (sibling, position)| match position {
+                SiblingPosition::Left => hash_pair(sibling, &current),
+                SiblingPosition::Right => hash_pair(&current, sibling),
+            });
+
+        root.as_ref() == &computed
+    }
+
+    // Whether the siblings are exactly the ones a tree of `leaf_count` leaves
+    // has on the path from `leaf_index`: one per level, on the side given by
+    // the index, except where an odd trailing node was promoted unchanged
+    fn has_expected_shape(&self) -> bool {
+        if self.leaf_index >= self.leaf_count {
+            return false;
+        }
+
+        let mut positions = self.siblings.iter().map(|(_, position)| *position);
+        let mut index = self.leaf_index;
+        let mut width = self.leaf_count;
+        while width > 1 {
+            let expected = if !index.is_multiple_of(2) {
+                Some(SiblingPosition::Left)
+            } else if index + 1 < width || self.duplicate_odd {
+                Some(SiblingPosition::Right)
+            } else {
+                None
+            };
+            if expected.is_some() && positions.next() != expected {
+                return false;
+            }
+
+            index /= 2;
+            width = width.div_ceil(2);
+        }
+
+        positions.next().is_none()
+    }
+}
+
+// Checks that `leaf` is part of the tree with `root`, e.g. a transaction
+// against the Merkle root of a `BlockHeader`. Proofs whose length or sibling
+// sides don't match the tree they claim to come from are rejected.
+pub fn verify_merkle_proof(leaf: &[u8], proof: &MerkleProof, root: &MerkleHash) -> bool {
+    proof.verify(leaf, root)
+}
+
+// Parent of one chunk of a level: the hash of both nodes, or of an odd
+// trailing node paired with itself or promoted as is
+fn combine_pair(pair: &[Sha256], duplicate_odd: bool) -> Sha256 {
+    match pair {
+        [lhs, rhs] => hash_pair(lhs, rhs),
+        [lhs] if duplicate_odd => hash_pair(lhs, lhs),
+        [lhs] => *lhs,
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+fn combine_level(level: &[Sha256], duplicate_odd: bool) -> Vec<Sha256> {
+    level
+        .chunks(2)
+        .map(|pair| combine_pair(pair, duplicate_odd))
+        .collect()
+}
+
+// Pairs are independent of each other, and collecting keeps them in order,
+// so the tree is identical to the serial one
+#[cfg(feature = "parallel")]
+fn combine_level(level: &[Sha256], duplicate_odd: bool) -> Vec<Sha256> {
+    use rayon::prelude::*;
+
+    level
+        .par_chunks(2)
+        .map(|pair| combine_pair(pair, duplicate_odd))
+        .collect()
+}
+
+// Transaction ids are already hashes, so they are used as the leaves as is
 impl From<&Vec<Transaction>> for MerkleTree {
     fn from(transactions: &Vec<Transaction>) -> Self {
-        let leaves = transactions
-            .iter()
-            .map(|tx| tx.id().as_ref().as_slice())
-            .collect::<Vec<&[u8]>>();
-        MerkleTree::new(&leaves)
+        MerkleTree::from_hashes(transactions.iter().map(|tx| *tx.id().as_ref()).collect())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::hash;
-    use crate::core::crypto::{as_hex, target_hash, MerkleTree};
+    use super::{
+        bits_to_target, from_hex, hash, hash160, hash256, hash_leaf, hash_pair, hash_parts,
+        hmac_sha256, ripemd160, target_to_bits, verify_merkle_proof, DoubleSha256Algorithm,
+        HashAlgorithm, Sha256Algorithm, Sha256Hasher, SiblingPosition,
+    };
+    use proptest::{
+        collection::{hash_set, vec},
+        prelude::*,
+    };
+
+    use crate::core::{
+        block::BlockHash,
+        crypto::{as_hex, target_hash, MerkleHash, MerkleTree, Sha256},
+        error::LuxError,
+    };
 
     #[test]
     fn hash_works() {
@@ -206,12 +709,215 @@ mod tests {
             "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
         );
     }
+
+    #[test]
+    fn hash_parts_matches_concatenation() {
+        assert_eq!(
+            hash_parts(&[b"hello".as_slice(), b" ", b"world"]),
+            hash(b"hello world")
+        );
+        assert_eq!(hash_parts(&[b"".as_slice(), b"hello"]), hash(b"hello"));
+        assert_eq!(hash_parts(&[]), hash(b""));
+    }
+
+    #[test]
+    fn hash256_works() {
+        let data = b"hello world";
+        assert_eq!(
+            hex::encode(hash256(data).as_ref()),
+            "bc62d4b80d9e36da29c16c5d4d9f11731f36052c72401a76c23c0fb5a9b74423"
+        );
+        assert_eq!(
+            hex::encode(hash256(b"hello").as_ref()),
+            "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50"
+        );
+        assert_eq!(hash256(b"hello"), hash(hash(b"hello").as_slice()));
+    }
+
+    #[test]
+    fn hasher_matches_one_shot_hash() {
+        let mut hasher = Sha256Hasher::new();
+        hasher.update(b"hello");
+        hasher.update(b" ");
+        hasher.update(b"world");
+
+        assert_eq!(hasher.clone().finalize(), hash(b"hello world"));
+        assert_eq!(hasher.finalize256(), hash256(b"hello world"));
+    }
+
+    #[test]
+    fn algorithms_match_hash_functions() {
+        assert_eq!(&Sha256Algorithm.digest(b"hello"), hash(b"hello").as_ref());
+        assert_eq!(
+            &DoubleSha256Algorithm.digest(b"hello"),
+            hash256(b"hello").as_ref()
+        );
+        assert_eq!(Sha256Algorithm.hash(b"hello"), hash(b"hello"));
+        assert_eq!(DoubleSha256Algorithm.hash(b"hello"), hash256(b"hello"));
+    }
+
+    #[test]
+    fn ct_eq_matches_eq() {
+        let a = hash(b"hello");
+        let mut bytes = *a.as_ref();
+        bytes[31] ^= 1;
+        let b = Sha256::new(bytes);
+
+        assert!(a.ct_eq(&a));
+        assert!(!a.ct_eq(&b));
+        assert!(!a.ct_eq(&hash(b"world")));
+
+        let mut first = *a.as_ref();
+        first[0] ^= 0x80;
+        assert!(!a.ct_eq(&Sha256::new(first)));
+        assert!(Sha256::new([0; 32]).ct_eq(&Sha256::new([0; 32])));
+    }
+
+    #[test]
+    fn hash160_vectors() {
+        assert_eq!(
+            as_hex(&ripemd160(b"abc")),
+            "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"
+        );
+        assert_eq!(
+            as_hex(&hash160(b"")),
+            "b472a266d0bd89c13706a4132ccfb16f7c3b9fcb"
+        );
+        assert_eq!(
+            as_hex(&hash160(b"abc")),
+            "bb1be98c142444d7a56aa3981c3942a978e4dc33"
+        );
+    }
+
+    // RFC 4231 test cases 1, 2 and 6, the last with a key longer than a block
+    #[test]
+    fn hmac_sha256_vectors() {
+        assert_eq!(
+            hmac_sha256(&[0x0b; 20], b"Hi There").to_string(),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        assert_eq!(
+            hmac_sha256(b"Jefe", b"what do ya want for nothing?").to_string(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )
+            .to_string(),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn leading_zero_bits_counts_big_endian() {
+        assert_eq!(Sha256::new([0; 32]).leading_zero_bits(), 256);
+        assert_eq!(Sha256::new([0xff; 32]).leading_zero_bits(), 0);
+
+        let mut bytes = [0xff; 32];
+        bytes[0] = 0x0f;
+        assert_eq!(Sha256::new(bytes).leading_zero_bits(), 4);
+        bytes[..3].copy_from_slice(&[0, 0, 0x0f]);
+        assert_eq!(Sha256::new(bytes).leading_zero_bits(), 20);
+
+        let mut last = [0; 32];
+        last[31] = 1;
+        assert_eq!(Sha256::new(last).leading_zero_bits(), 255);
+    }
+
+    #[test]
+    fn from_hex_accepts_prefix_and_mixed_case() {
+        let expected = hash(b"hello world");
+        let lower = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+        assert_eq!(from_hex(lower).unwrap(), expected);
+        assert_eq!(from_hex(&format!("0x{}", lower)).unwrap(), expected);
+        assert_eq!(
+            from_hex(&format!("0X{}", lower.to_uppercase())).unwrap(),
+            expected
+        );
+        assert_eq!(
+            from_hex("B94d27B9934D3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9").unwrap(),
+            expected
+        );
+        assert_eq!(from_hex(&format!("  0x{}\n", lower)).unwrap(), expected);
+        assert_eq!(from_hex(&format!("\t{} ", lower)).unwrap(), expected);
+    }
+
+    #[test]
+    fn try_from_checks_length() {
+        let bytes = hash(b"hello").as_slice().to_vec();
+
+        assert_eq!(Sha256::try_from(bytes.as_slice()).unwrap(), hash(b"hello"));
+        assert_eq!(Sha256::try_from(bytes.clone()).unwrap(), hash(b"hello"));
+        assert_eq!(
+            Sha256::try_from(&bytes[..31]).unwrap_err().to_string(),
+            "Failed to parse hash: Invalid sha length. Expected: 32 but got: 31"
+        );
+        assert!(matches!(
+            Sha256::try_from(vec![0; 33]),
+            Err(LuxError::HashParse(_))
+        ));
+    }
+
+    #[test]
+    fn from_str_parses_hex() {
+        let sha = hash(b"hello");
+
+        assert_eq!(sha.to_string().parse::<Sha256>().unwrap(), sha);
+        assert!(matches!(
+            "0xb94d27".parse::<Sha256>(),
+            Err(LuxError::HashParse(_))
+        ));
+    }
+
+    #[test]
+    fn from_hex_rejects_wrong_length() {
+        assert_eq!(
+            from_hex("0xb94d27").unwrap_err().to_string(),
+            "Failed to parse hash: Invalid sha length. Expected: 32 but got: 3 in: 0xb94d27"
+        );
+        assert!(matches!(from_hex("0x"), Err(LuxError::HashParse(_))));
+        assert!(matches!(from_hex("0xzz"), Err(LuxError::HashParse(_))));
+    }
+
+    #[test]
+    fn deserialize_reports_expected_format() {
+        let err = serde_json::from_str::<Sha256>("\"b94d27\"").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid length 6, expected a 64-character hex-encoded SHA-256 hash at line 1 column 8"
+        );
+
+        let err = serde_json::from_str::<Sha256>("42").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("expected a 64-character hex-encoded SHA-256 hash"));
+
+        let hex = format!("\"{}\"", hash(b"hello"));
+        assert_eq!(
+            serde_json::from_str::<Sha256>(&hex).unwrap(),
+            hash(b"hello")
+        );
+    }
+
+    #[test]
+    fn binary_encoding_is_raw_bytes() {
+        let sha = hash(b"hello");
+        let bytes = bincode::serialize(&sha).unwrap();
+
+        assert_eq!(bytes, sha.as_slice());
+        assert_eq!(bincode::deserialize::<Sha256>(&bytes).unwrap(), sha);
+        assert!(bincode::deserialize::<Sha256>(&bytes[..31]).is_err());
+    }
+
     #[test]
     fn merkle_tree_even() {
         let root_node = MerkleTree::new(&vec![b"hello", b"world"]);
         assert_eq!(
             as_hex(root_node.as_ref().as_slice()),
-            "7305db9b2abccd706c256db3d97e5ff48d677cfe4d3a5904afb7da0e3950e1e2"
+            "24233339aadcedf287d262413f03c028eb8db397edd32a2878091151b99bf20f"
         );
     }
 
@@ -228,7 +934,7 @@ mod tests {
         ]);
         assert_eq!(
             as_hex(root_node.as_ref().as_slice()),
-            "4ba2b808c60bdee5df9da358021b50ae56f544682c7931fcc032d2ca323c13bb"
+            "275eee063792b7028b8d0a6ce584fb9ba30609e60ba2670463f5e355ce66949f"
         )
     }
 
@@ -246,35 +952,405 @@ mod tests {
         ]);
         assert_eq!(
             as_hex(root_node.as_ref().as_slice()),
-            "4ba2b808c60bdee5df9da358021b50ae56f544682c7931fcc032d2ca323c13bb"
+            "275eee063792b7028b8d0a6ce584fb9ba30609e60ba2670463f5e355ce66949f"
         )
     }
 
+    #[test]
+    fn merkle_tree_without_duplication() {
+        let odd: Vec<&[u8]> = vec![b"hello", b"world", b"again"];
+        let padded: Vec<&[u8]> = vec![b"hello", b"world", b"again", b"again"];
+
+        let tree = MerkleTree::new_with_options(&odd, false);
+        assert_ne!(
+            tree.root(),
+            MerkleTree::new_with_options(&padded, false).root()
+        );
+        assert_eq!(
+            tree.root().as_ref(),
+            &hash_pair(
+                &hash_pair(&hash_leaf(b"hello"), &hash_leaf(b"world")),
+                &hash_leaf(b"again")
+            )
+        );
+        assert!(!tree.duplicates_odd_nodes());
+
+        for (i, leaf) in odd.iter().enumerate() {
+            assert!(tree.proof(i).unwrap().verify(leaf, tree.as_ref()));
+        }
+        assert_eq!(tree.proof(2).unwrap().siblings().len(), 1);
+    }
+
+    #[test]
+    fn interior_nodes_cannot_pose_as_leaves() {
+        let tree = MerkleTree::new(&vec![b"a", b"b", b"c", b"d"]);
+        // The two children of the root, passed off as a single leaf
+        let children = &tree.levels()[1];
+        let forged = [children[0].as_slice(), children[1].as_slice()].concat();
+
+        assert_ne!(
+            MerkleTree::new(&vec![forged.as_slice()]).root(),
+            tree.root()
+        );
+    }
+
+    #[test]
+    fn merkle_tree_detects_duplication() {
+        assert!(MerkleTree::new(&vec![b"hello", b"world", b"again"]).duplicates_odd_nodes());
+        assert!(!MerkleTree::new(&vec![b"hello", b"world"]).duplicates_odd_nodes());
+        assert!(!MerkleTree::new(&vec![b"hello"]).duplicates_odd_nodes());
+        assert!(MerkleTree::new(&vec![b"a", b"b", b"c", b"d", b"e", b"f"]).duplicates_odd_nodes());
+    }
+
     #[test]
     fn target_hash_test() {
         assert_eq!(
-            as_hex(target_hash(0).as_slice()),
+            as_hex(target_hash(0).unwrap().as_slice()),
             "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
         );
         assert_eq!(
-            as_hex(target_hash(4).as_slice()),
+            as_hex(target_hash(4).unwrap().as_slice()),
             "0fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
         );
         assert_eq!(
-            as_hex(target_hash(8).as_slice()),
+            as_hex(target_hash(8).unwrap().as_slice()),
             "00ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
         );
         assert_eq!(
-            as_hex(target_hash(12).as_slice()),
+            as_hex(target_hash(12).unwrap().as_slice()),
             "000fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
         );
         assert_eq!(
-            as_hex(target_hash(16).as_slice()),
+            as_hex(target_hash(16).unwrap().as_slice()),
             "0000ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
         );
         assert_eq!(
-            as_hex(target_hash(20).as_slice()),
+            as_hex(target_hash(20).unwrap().as_slice()),
             "00000fffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
         );
+        assert_eq!(
+            as_hex(target_hash(255).unwrap().as_slice()),
+            "0000000000000000000000000000000000000000000000000000000000000001"
+        );
+        assert_eq!(target_hash(256).unwrap(), BlockHash::zero());
+        assert!(matches!(target_hash(257), Err(LuxError::InvalidTarget(_))));
+        assert!(target_hash(u32::MAX).is_err());
+    }
+
+    #[test]
+    fn merkle_tree_retains_levels() {
+        let tree = MerkleTree::new(&vec![b"hello", b"world", b"again"]);
+
+        assert_eq!(
+            tree.leaves(),
+            &[
+                hash_leaf(b"hello"),
+                hash_leaf(b"world"),
+                hash_leaf(b"again")
+            ]
+        );
+        assert_eq!(
+            tree.levels().iter().map(Vec::len).collect::<Vec<usize>>(),
+            vec![3, 2, 1]
+        );
+        assert_eq!(tree.root().as_ref(), &tree.levels()[2][0]);
+        assert_eq!(tree.root(), tree.as_ref());
+        assert_eq!(tree.leaf_count(), 3);
+        assert_eq!(tree.depth(), 2);
+        assert_eq!(tree.proof(2).unwrap().siblings().len(), tree.depth());
+
+        let unbalanced = MerkleTree::new_with_options(&[b"a", b"b", b"c", b"d", b"e"], false);
+        assert_eq!(unbalanced.depth(), 3);
+        assert_eq!(unbalanced.proof(4).unwrap().siblings().len(), 1);
+    }
+
+    #[test]
+    fn merkle_proof_verifies_every_leaf() {
+        let leaves: Vec<&[u8]> = vec![
+            b"programmed",
+            b"to",
+            b"work",
+            b"and",
+            b"not",
+            b"to",
+            b"feel",
+        ];
+        let tree = MerkleTree::new(&leaves);
+
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = tree.proof(i).unwrap();
+            assert_eq!(proof.siblings().len(), 3);
+            assert!(proof.verify(leaf, tree.as_ref()));
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_levels_match_serial() {
+        let leaves = (0..1001u32)
+            .map(|i| hash(&i.to_le_bytes()))
+            .collect::<Vec<Sha256>>();
+
+        for duplicate_odd in [true, false] {
+            let tree = MerkleTree::build(leaves.clone(), duplicate_odd);
+            let mut level = leaves.clone();
+            for parallel in tree.levels() {
+                assert_eq!(parallel, &level);
+                level = level
+                    .chunks(2)
+                    .map(|pair| super::combine_pair(pair, duplicate_odd))
+                    .collect();
+            }
+        }
+    }
+
+    #[test]
+    fn merkle_tree_from_hashes() {
+        let leaves: Vec<&[u8]> = vec![b"hello", b"world", b"again"];
+        let hashes = leaves
+            .iter()
+            .map(|leaf| hash_leaf(leaf))
+            .collect::<Vec<Sha256>>();
+
+        let tree = MerkleTree::from_hashes(hashes.clone());
+        assert_eq!(tree.root(), MerkleTree::new(&leaves).root());
+        assert_eq!(tree.leaves(), hashes.as_slice());
+        for (i, leaf_hash) in hashes.iter().enumerate() {
+            assert!(tree.proof(i).unwrap().verify_hash(leaf_hash, tree.as_ref()));
+        }
+
+        let single = MerkleTree::from_hashes(vec![hashes[0]]);
+        assert_eq!(single.root().as_ref(), &hashes[0]);
+    }
+
+    #[test]
+    fn merkle_tree_empty_and_single() {
+        let empty = MerkleTree::new(&vec![]);
+        assert_eq!(empty.root(), &MerkleTree::empty_root());
+        assert_eq!(
+            as_hex(empty.root().as_slice()),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert!(empty.leaves().is_empty());
+        assert!(empty.proof(0).is_none());
+        assert!(!empty.duplicates_odd_nodes());
+        assert_eq!(MerkleTree::from(&vec![]).root(), empty.root());
+        assert!(matches!(
+            MerkleTree::try_new(&[]),
+            Err(LuxError::EmptyMerkleTree)
+        ));
+
+        let single = MerkleTree::new(&vec![b"hello"]);
+        assert_eq!(single.root().as_ref(), &hash_leaf(b"hello"));
+        assert_eq!(
+            MerkleTree::try_new(&[b"hello"]).unwrap().root(),
+            single.root()
+        );
+        assert_eq!(single.levels().len(), 1);
+        assert_eq!((single.depth(), single.leaf_count()), (0, 1));
+        assert_eq!((empty.depth(), empty.leaf_count()), (0, 0));
+        let proof = single.proof(0).unwrap();
+        assert!(proof.siblings().is_empty());
+        assert!(proof.verify(b"hello", single.as_ref()));
+    }
+
+    #[test]
+    fn merkle_proof_rejects_wrong_leaf_or_root() {
+        let tree = MerkleTree::new(&vec![b"hello", b"world", b"again"]);
+        let proof = tree.proof(1).unwrap();
+
+        assert!(!proof.verify(b"hello", tree.as_ref()));
+        assert!(!proof.verify(b"world", &MerkleHash::new(hash(b"world"))));
+        assert!(tree.proof(3).is_none());
+    }
+
+    #[test]
+    fn merkle_proof_must_match_tree_shape() {
+        let leaves: Vec<&[u8]> = vec![b"a", b"b", b"c", b"d", b"e"];
+        let tree = MerkleTree::new(&leaves);
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = tree.proof(i).unwrap();
+            assert_eq!(proof.leaf_index(), i);
+            assert_eq!(proof.leaf_count(), 5);
+            assert!(verify_merkle_proof(leaf, &proof, tree.root()));
+        }
+
+        let proof = tree.proof(4).unwrap();
+        let mut extended = proof.clone();
+        extended
+            .siblings
+            .push((hash(b"extra"), SiblingPosition::Right));
+        assert!(!verify_merkle_proof(b"e", &extended, tree.root()));
+
+        let mut truncated = proof.clone();
+        truncated.siblings.pop();
+        assert!(!verify_merkle_proof(b"e", &truncated, tree.root()));
+
+        let mut flipped = proof.clone();
+        flipped.siblings[0].1 = SiblingPosition::Left;
+        assert!(!verify_merkle_proof(b"e", &flipped, tree.root()));
+
+        let mut out_of_range = proof;
+        out_of_range.leaf_index = 5;
+        assert!(!verify_merkle_proof(b"e", &out_of_range, tree.root()));
+    }
+
+    #[test]
+    fn bits_to_target_vectors() {
+        assert_eq!(
+            as_hex(bits_to_target(0x1d00ffff).unwrap().as_slice()),
+            "00000000ffff0000000000000000000000000000000000000000000000000000"
+        );
+        assert_eq!(
+            as_hex(bits_to_target(0x0300ffff).unwrap().as_slice()),
+            "000000000000000000000000000000000000000000000000000000000000ffff"
+        );
+        assert_eq!(
+            as_hex(bits_to_target(0x02008000).unwrap().as_slice()),
+            "0000000000000000000000000000000000000000000000000000000000000080"
+        );
+        assert_eq!(bits_to_target(0).unwrap(), target_hash(256).unwrap());
+    }
+
+    #[test]
+    fn bits_round_trip() {
+        for bits in [
+            0x1d00ffff, 0x1b0404cb, 0x207fffff, 0x0300ffff, 0x02008000, 0,
+        ] {
+            assert_eq!(target_to_bits(&bits_to_target(bits).unwrap()), bits);
+        }
+
+        for n_zero_bits in [0, 1, 7, 8, 20, 33, 255] {
+            let target = target_hash(n_zero_bits).unwrap();
+            let bits = target_to_bits(&target);
+            // Only the top 23 bits of the mantissa survive the round trip
+            assert!(bits_to_target(bits).unwrap() <= target);
+        }
+    }
+
+    #[test]
+    fn bits_rejects_non_canonical_encodings() {
+        // Negative
+        assert!(matches!(
+            bits_to_target(0x1d800000),
+            Err(LuxError::InvalidTarget(_))
+        ));
+        // Overflows 256 bits
+        assert!(matches!(
+            bits_to_target(0x2200ffff),
+            Err(LuxError::InvalidTarget(_))
+        ));
+        // Leading zero byte in the mantissa
+        assert!(matches!(
+            bits_to_target(0x1e0000ff),
+            Err(LuxError::InvalidTarget(_))
+        ));
+        // Zero target with a non zero exponent
+        assert!(matches!(
+            bits_to_target(0x01000000),
+            Err(LuxError::InvalidTarget(_))
+        ));
+    }
+
+    // Straightforward restatement of the tree rules, indexing each level
+    // directly instead of chunking it
+    fn reference_root(leaves: &[Vec<u8>], duplicate_odd: bool) -> Sha256 {
+        if leaves.is_empty() {
+            return hash(&[]);
+        }
+
+        let mut level = leaves
+            .iter()
+            .map(|leaf| hash_leaf(leaf))
+            .collect::<Vec<Sha256>>();
+        while level.len() > 1 {
+            let mut next = vec![];
+            let mut i = 0;
+            while i < level.len() {
+                if i + 1 < level.len() {
+                    next.push(hash_pair(&level[i], &level[i + 1]));
+                } else if duplicate_odd {
+                    next.push(hash_pair(&level[i], &level[i]));
+                } else {
+                    next.push(level[i]);
+                }
+                i += 2;
+            }
+            level = next;
+        }
+        level[0]
+    }
+
+    fn root_of(leaves: &[Vec<u8>], duplicate_odd: bool) -> MerkleHash {
+        let leaves = leaves.iter().map(Vec::as_slice).collect::<Vec<&[u8]>>();
+        MerkleTree::new_with_options(&leaves, duplicate_odd)
+            .root()
+            .clone()
+    }
+
+    fn leaves(max: usize) -> impl Strategy<Value = Vec<Vec<u8>>> {
+        vec(vec(any::<u8>(), 0..16), 0..max)
+    }
+
+    // Leaves that are pairwise different, so no two leaf lists can share a
+    // root through odd-node duplication
+    fn distinct_leaves(max: usize) -> impl Strategy<Value = Vec<Vec<u8>>> {
+        hash_set(vec(any::<u8>(), 0..16), 0..max).prop_map(|set| set.into_iter().collect())
+    }
+
+    proptest! {
+        #[test]
+        fn single_leaf_root_is_leaf_hash(leaf in vec(any::<u8>(), 0..64)) {
+            let tree = MerkleTree::new(&vec![leaf.as_slice()]);
+            prop_assert_eq!(tree.root(), &MerkleHash::new(hash_leaf(&leaf)));
+            prop_assert!(!tree.duplicates_odd_nodes());
+        }
+
+        #[test]
+        fn root_matches_reference(leaves in leaves(40), duplicate_odd in any::<bool>()) {
+            prop_assert_eq!(
+                root_of(&leaves, duplicate_odd),
+                MerkleHash::new(reference_root(&leaves, duplicate_odd))
+            );
+        }
+
+        #[test]
+        fn appending_changes_root(
+            leaves in distinct_leaves(40),
+            extra in vec(any::<u8>(), 0..16),
+        ) {
+            prop_assume!(!leaves.contains(&extra));
+            let mut appended = leaves.clone();
+            appended.push(extra);
+
+            prop_assert_ne!(root_of(&leaves, true), root_of(&appended, true));
+            prop_assert_ne!(root_of(&leaves, false), root_of(&appended, false));
+        }
+
+        #[test]
+        fn reordering_changes_root(
+            leaves in distinct_leaves(40),
+            i in any::<prop::sample::Index>(),
+            j in any::<prop::sample::Index>(),
+        ) {
+            prop_assume!(leaves.len() >= 2);
+            let (i, j) = (i.index(leaves.len()), j.index(leaves.len()));
+            prop_assume!(i != j);
+            let mut reordered = leaves.clone();
+            reordered.swap(i, j);
+
+            prop_assert_ne!(root_of(&leaves, true), root_of(&reordered, true));
+            prop_assert_ne!(root_of(&leaves, false), root_of(&reordered, false));
+        }
+
+        #[test]
+        fn proofs_verify_every_leaf(leaves in leaves(40), duplicate_odd in any::<bool>()) {
+            let slices = leaves.iter().map(Vec::as_slice).collect::<Vec<&[u8]>>();
+            let tree = MerkleTree::new_with_options(&slices, duplicate_odd);
+            for (i, leaf) in leaves.iter().enumerate() {
+                prop_assert!(tree.proof(i).unwrap().verify(leaf, tree.root()));
+            }
+            prop_assert!(tree.proof(leaves.len()).is_none());
+        }
     }
 }
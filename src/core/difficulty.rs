@@ -0,0 +1,201 @@
+use crate::core::{
+    block::{BlockHash, BlockHeader},
+    consensus::ConsensusParams,
+    crypto,
+    error::LuxResult,
+};
+
+// Largest factor the target may move by in a single retarget
+pub const MAX_ADJUSTMENT_FACTOR: u64 = 4;
+// Difficulty counts the leading zero bits of the target
+pub const MAX_DIFFICULTY: u32 = 256;
+
+// A difficulty is a count of leading zero bits, not a compact threshold (see
+// `crypto::bits_to_target` for those). Its target is the largest hash with
+// that many leading zero bits, and a hash meets it exactly when it has at
+// least that many.
+pub fn difficulty_to_target(difficulty: u32) -> LuxResult<BlockHash> {
+    crypto::target_hash(difficulty)
+}
+
+// The inverse of `difficulty_to_target`. Targets that aren't all ones after
+// their leading zeros fall between two difficulties and get the easier one,
+// so every hash meeting the target also meets the returned difficulty.
+pub fn target_to_difficulty(target: &BlockHash) -> u32 {
+    target.as_ref().leading_zero_bits()
+}
+
+// Computes the difficulty of the next block from the time it took to mine the
+// last `params.difficulty_window` headers. Since every difficulty step halves
+// the target, the difficulty moves by log2(expected / actual) rounded towards
+// zero, with the ratio clamped to `MAX_ADJUSTMENT_FACTOR` either way.
+pub fn retarget(last_headers: &[BlockHeader], params: &ConsensusParams) -> u32 {
+    let window = params.difficulty_window;
+    let headers = &last_headers[last_headers.len().saturating_sub(window)..];
+    let (first, last) = match headers {
+        [] => return 0,
+        [only] => return only.difficulty(),
+        [first, .., last] => (first, last),
+    };
+
+    let expected = params.target_spacing_secs as u64 * (headers.len() - 1) as u64;
+    if expected == 0 {
+        return last.difficulty();
+    }
+
+    // Timestamps can go backwards, which counts as the fastest possible period
+    let actual = (last.timestamp().as_secs() as u64)
+        .saturating_sub(first.timestamp().as_secs() as u64)
+        .clamp(
+            (expected / MAX_ADJUSTMENT_FACTOR).max(1),
+            expected * MAX_ADJUSTMENT_FACTOR,
+        );
+
+    let current = last.difficulty();
+    if actual < expected {
+        let steps = (1..).take_while(|step| actual << step <= expected).count() as u32;
+        (current + steps).min(MAX_DIFFICULTY)
+    } else {
+        let steps = (1..).take_while(|step| expected << step <= actual).count() as u32;
+        current.saturating_sub(steps)
+    }
+}
+
+// Difficulty the block at `height` has to declare. It only changes on the
+// first block of every `params.difficulty_window` blocks, retargeting from
+// the headers of the window that just ended, which `window` returns oldest
+// first. Every other block keeps the difficulty of its parent.
+pub fn expected_difficulty(
+    height: u32,
+    parent: &BlockHeader,
+    window: impl FnOnce() -> Vec<BlockHeader>,
+    params: &ConsensusParams,
+) -> u32 {
+    if (height as usize).is_multiple_of(params.difficulty_window.max(1)) {
+        retarget(&window(), params)
+    } else {
+        parent.difficulty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        difficulty_to_target, expected_difficulty, retarget, target_to_difficulty, MAX_DIFFICULTY,
+    };
+    use crate::core::{
+        block::{BlockHash, BlockHeader},
+        consensus::ConsensusParams,
+        crypto::{self, MerkleHash},
+        timestamp::Timestamp,
+    };
+
+    fn params(difficulty_window: usize) -> ConsensusParams {
+        ConsensusParams {
+            target_spacing_secs: 60,
+            difficulty_window,
+            ..ConsensusParams::mainnet()
+        }
+    }
+
+    fn headers(difficulty: u32, timestamps: &[u32]) -> Vec<BlockHeader> {
+        timestamps
+            .iter()
+            .map(|timestamp| {
+                BlockHeader::new(
+                    BlockHash::zero(),
+                    MerkleHash::new(crypto::hash(b"transactions")),
+                    Timestamp::new(*timestamp),
+                    difficulty,
+                    0,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn on_schedule_keeps_difficulty() {
+        let headers = headers(10, &[0, 60, 120, 180, 240]);
+        assert_eq!(retarget(&headers, &params(5)), 10);
+    }
+
+    #[test]
+    fn fast_blocks_raise_difficulty() {
+        assert_eq!(
+            retarget(&headers(10, &[0, 30, 60, 90, 120]), &params(5)),
+            11
+        );
+        assert_eq!(retarget(&headers(10, &[0, 15, 30, 45, 60]), &params(5)), 12);
+        // Clamped to a 4x adjustment
+        assert_eq!(retarget(&headers(10, &[0, 1, 2, 3, 4]), &params(5)), 12);
+    }
+
+    #[test]
+    fn slow_blocks_lower_difficulty() {
+        assert_eq!(
+            retarget(&headers(10, &[0, 120, 240, 360, 480]), &params(5)),
+            9
+        );
+        // Clamped to a 4x adjustment
+        assert_eq!(
+            retarget(&headers(10, &[0, 600, 1200, 1800, 2400]), &params(5)),
+            8
+        );
+        assert_eq!(
+            retarget(&headers(1, &[0, 600, 1200, 1800, 2400]), &params(5)),
+            0
+        );
+    }
+
+    #[test]
+    fn backwards_timestamps() {
+        let headers = headers(10, &[1000, 900, 800, 700, 600]);
+        assert_eq!(retarget(&headers, &params(5)), 12);
+    }
+
+    #[test]
+    fn only_the_window_is_considered() {
+        let headers = headers(10, &[0, 10_000, 10_060, 10_120, 10_180]);
+        assert_eq!(retarget(&headers, &params(4)), 10);
+        assert_eq!(retarget(&headers, &params(5)), 8);
+    }
+
+    #[test]
+    fn retargets_once_per_window() {
+        let headers = headers(10, &[0, 30, 60, 90, 120]);
+        let parent = headers.last().unwrap();
+        for height in [1, 4, 6, 9] {
+            let window = || panic!("only read when retargeting");
+            assert_eq!(expected_difficulty(height, parent, window, &params(5)), 10);
+        }
+        for height in [5, 10] {
+            let window = || headers.clone();
+            assert_eq!(expected_difficulty(height, parent, window, &params(5)), 11);
+        }
+    }
+
+    #[test]
+    fn short_histories() {
+        assert_eq!(retarget(&[], &params(5)), 0);
+        assert_eq!(retarget(&headers(7, &[0]), &params(5)), 7);
+    }
+
+    #[test]
+    fn difficulty_and_target_are_inverses() {
+        for difficulty in 0..=MAX_DIFFICULTY {
+            let target = difficulty_to_target(difficulty).unwrap();
+            assert_eq!(target_to_difficulty(&target), difficulty);
+        }
+        assert!(difficulty_to_target(MAX_DIFFICULTY + 1).is_err());
+
+        // Between the targets of 9 and 10 zero bits, so only 9 is guaranteed to
+        // be met
+        let mut between = [0xff; 32];
+        between[0] = 0;
+        between[1] = 0x40;
+        let between = BlockHash::new(crypto::Sha256::new(between));
+        assert_eq!(target_to_difficulty(&between), 9);
+        assert!(between <= difficulty_to_target(9).unwrap());
+        assert!(between > difficulty_to_target(10).unwrap());
+    }
+}
@@ -0,0 +1,57 @@
+use crate::core::{consensus::ConsensusParams, luxcoin::Luxcoin};
+
+// Reward for mining the block at `height`: the initial subsidy halved once per
+// completed halving interval, rounding down until it reaches zero
+pub fn block_subsidy(height: u32, params: &ConsensusParams) -> Luxcoin {
+    let halvings = height / params.halving_interval.max(1);
+    if halvings >= i64::BITS {
+        return Luxcoin::ZERO;
+    }
+
+    Luxcoin::new(params.initial_subsidy.as_i64() >> halvings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::block_subsidy;
+    use crate::core::{consensus::ConsensusParams, luxcoin::Luxcoin};
+
+    fn lux(amount: &str) -> Luxcoin {
+        amount.parse().unwrap()
+    }
+
+    #[test]
+    fn subsidy_halves_every_interval() {
+        let params = ConsensusParams::mainnet();
+
+        assert_eq!(block_subsidy(0, &params), lux("50"));
+        assert_eq!(block_subsidy(209_999, &params), lux("50"));
+        assert_eq!(block_subsidy(210_000, &params), lux("25"));
+        assert_eq!(block_subsidy(420_000, &params), lux("12.5"));
+        assert_eq!(block_subsidy(5 * 210_000, &params), lux("1.5625"));
+        assert_eq!(block_subsidy(32 * 210_000, &params), Luxcoin::new(1));
+        assert_eq!(block_subsidy(33 * 210_000, &params), Luxcoin::new(0));
+    }
+
+    #[test]
+    fn subsidy_floors_to_zero() {
+        let params = ConsensusParams {
+            initial_subsidy: Luxcoin::new(i64::MAX),
+            halving_interval: 1,
+            ..ConsensusParams::mainnet()
+        };
+
+        assert_eq!(block_subsidy(62, &params), Luxcoin::new(1));
+        assert_eq!(block_subsidy(63, &params), Luxcoin::new(0));
+        assert_eq!(block_subsidy(64, &params), Luxcoin::new(0));
+        assert_eq!(block_subsidy(u32::MAX, &params), Luxcoin::new(0));
+    }
+
+    #[test]
+    fn testnet_halves_quickly() {
+        let params = ConsensusParams::testnet();
+
+        assert_eq!(block_subsidy(149, &params), lux("50"));
+        assert_eq!(block_subsidy(150, &params), lux("25"));
+    }
+}
@@ -1,13 +1,121 @@
-use std::error;
+use std::{error, time::Duration};
 
 use thiserror::Error;
 
+use crate::core::{
+    block::BlockHash,
+    crypto::MerkleHash,
+    luxcoin::Luxcoin,
+    transaction::{OutputIndex, TransactionId},
+};
+
 pub type LuxResult<T> = Result<T, LuxError>;
 
 #[derive(Debug, Error)]
 pub enum LuxError {
-    #[error("0")]
+    #[error("Invalid transaction: {0}")]
     InvalidTransaction(String),
+    #[error("Invalid block: {0}")]
+    InvalidBlock(String),
+    #[error("Invalid coinbase: {0}")]
+    InvalidCoinbase(String),
+    #[error("Merkle root mismatch. Expected: {expected} but got: {actual}")]
+    MerkleRootMismatch {
+        expected: MerkleHash,
+        actual: MerkleHash,
+    },
+    #[error("Merkle tree needs at least one leaf")]
+    EmptyMerkleTree,
+    #[error("Checkpoint mismatch at height {height}. Expected: {expected} but got: {actual}")]
+    CheckpointMismatch {
+        height: u32,
+        expected: BlockHash,
+        actual: BlockHash,
+    },
+    #[error("Difficulty mismatch at height {height}. Expected: {expected} but got: {actual}")]
+    DifficultyMismatch {
+        height: u32,
+        expected: u32,
+        actual: u32,
+    },
+    #[error("Block at height {height} failed verification: {reason}")]
+    ChainVerification { height: u32, reason: String },
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+    #[error("Invalid target: {0}")]
+    InvalidTarget(String),
+    #[error("Block hash: {hash} does not meet target: {target}")]
+    ProofOfWorkFailed { hash: BlockHash, target: BlockHash },
+    #[error("Output {utxo}:{index} has already been spent")]
+    DoubleSpend {
+        utxo: TransactionId,
+        index: OutputIndex,
+    },
+    #[error("Output {utxo}:{index} is spent without its owner's signature")]
+    InvalidSignature {
+        utxo: TransactionId,
+        index: OutputIndex,
+    },
+    #[error("Replacement rejected: {0}")]
+    ReplacementRejected(String),
+    #[error("Output {utxo}:{index} does not exist")]
+    MissingOutput {
+        utxo: TransactionId,
+        index: OutputIndex,
+    },
+    #[error("Transaction id mismatch. Expected: {expected} but got: {actual}")]
+    TransactionIdMismatch {
+        expected: TransactionId,
+        actual: TransactionId,
+    },
+    #[error("Failed to parse hash: {0}")]
+    HashParse(String),
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+    #[error("Insufficient funds. Required: {required} but only {available} available")]
+    InsufficientFunds {
+        required: Luxcoin,
+        available: Luxcoin,
+    },
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Invalid base58: {0}")]
+    InvalidBase58(String),
+    #[error("Base58check checksum mismatch. Expected: {expected} but got: {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(Luxcoin),
+    #[error("Failed to parse amount: {0}")]
+    AmountParse(String),
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+    #[error("Invalid message: {0}")]
+    InvalidMessage(String),
+    #[error("Incomplete message. Needed: {needed} bytes but got: {available}")]
+    IncompleteMessage { needed: usize, available: usize },
+    #[error("Connection error: {0}")]
+    Connection(String),
+    #[error("Peer sent nothing for {0:?}")]
+    PeerTimeout(Duration),
+    #[error("Block: {0} has been pruned")]
+    BlockPruned(BlockHash),
+    #[error("Storage error: {0}")]
+    Storage(String),
+    #[error("Mining was cancelled")]
+    MiningCancelled,
+    #[error("Exhausted the nonce and timestamp search space while mining")]
+    MiningExhausted,
     #[error("Unknown error: {0}")]
     Unknown(Box<dyn error::Error>),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::LuxError;
+
+    #[test]
+    fn invalid_transaction_renders_message() {
+        let error = LuxError::InvalidTransaction("missing inputs".to_string());
+        assert_eq!(error.to_string(), "Invalid transaction: missing inputs");
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display},
+};
+
+use crate::core::luxcoin::Luxcoin;
+
+// Fee paid per weight unit, see `Transaction::weight`. Kept as the fee and
+// weight it was computed from so that comparisons are exact. Rates compare by
+// cross multiplying, `fee_a * weight_b` against `fee_b * weight_a`, where
+// dividing first would round distinct rates down to the same value.
+#[derive(Copy, Clone, Debug)]
+pub struct FeeRate {
+    fee: Luxcoin,
+    weight: usize,
+}
+
+impl FeeRate {
+    // A zero weight is treated as one weight unit
+    pub fn new(fee: Luxcoin, weight: usize) -> Self {
+        Self {
+            fee,
+            weight: weight.max(1),
+        }
+    }
+
+    pub fn fee(&self) -> Luxcoin {
+        self.fee
+    }
+
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    // Fee per 1000 weight units, rounded toward zero
+    pub fn per_kwu(&self) -> Luxcoin {
+        let per_kwu = self.fee.as_i64() as i128 * 1000 / self.weight as i128;
+        Luxcoin::new(per_kwu.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    }
+}
+
+impl Ord for FeeRate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let lhs = self.fee.as_i64() as i128 * other.weight as i128;
+        let rhs = other.fee.as_i64() as i128 * self.weight as i128;
+        lhs.cmp(&rhs)
+    }
+}
+
+impl PartialOrd for FeeRate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Equal rates are equal whatever fee and weight they came from, e.g. 2 units
+// over 100 weight units and 4 units over 200
+impl PartialEq for FeeRate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FeeRate {}
+
+impl Display for FeeRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/kWU", self.per_kwu())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FeeRate;
+    use crate::core::luxcoin::Luxcoin;
+
+    fn rate(fee: i64, weight: usize) -> FeeRate {
+        FeeRate::new(Luxcoin::new(fee), weight)
+    }
+
+    #[test]
+    fn orders_rates_that_integer_division_ties() {
+        // Both round down to 1 unit per weight unit
+        let (higher, lower) = (rate(199, 100), rate(101, 100));
+        assert_eq!(199 / 100, 101 / 100);
+        assert!(higher > lower);
+
+        // Both round down to 0 units per weight unit
+        let (higher, lower) = (rate(250, 251), rate(1, 251));
+        assert!(higher > lower);
+        assert!(rate(3, 1000) > rate(1, 1000));
+        assert!(rate(1, 3) < rate(1, 2));
+    }
+
+    #[test]
+    fn equal_rates_compare_equal() {
+        assert_eq!(rate(2, 100), rate(4, 200));
+        assert_ne!(rate(2, 100), rate(2, 101));
+        assert_eq!(rate(5, 0), rate(5, 1));
+    }
+
+    #[test]
+    fn per_kwu_scales_before_dividing() {
+        assert_eq!(rate(1, 250).per_kwu(), Luxcoin::new(4));
+        assert_eq!(rate(199, 100).per_kwu(), Luxcoin::new(1990));
+        assert_eq!(rate(i64::MAX, 1).per_kwu(), Luxcoin::MAX);
+        assert_eq!(rate(1, 250).to_string(), "0.00000004 LUX/kWU");
+    }
+}
@@ -0,0 +1,219 @@
+use std::f64::consts::LN_2;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    crypto,
+    error::{LuxError, LuxResult},
+    transaction::{Transaction, TransactionOutput},
+};
+
+// Bounds on what a peer may ask a node to check every transaction against
+pub const MAX_FILTER_SIZE: usize = 36_000;
+pub const MAX_HASH_FUNCTIONS: u32 = 50;
+
+// Probabilistic set of byte strings. `contains` never misses an inserted item,
+// but may report items that were never inserted, which lets a light client
+// describe what it is interested in without revealing it exactly.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "BloomFilterData")]
+pub struct BloomFilter {
+    #[serde(with = "serde_bytes")]
+    bits: Vec<u8>,
+    hash_functions: u32,
+}
+
+impl BloomFilter {
+    // Sizes the filter so that once `expected_items` are inserted, unrelated
+    // items match with roughly `false_positive_rate` probability. The size is
+    // capped at `MAX_FILTER_SIZE` bytes, raising the rate for large sets.
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> LuxResult<Self> {
+        if expected_items == 0 {
+            return Err(LuxError::InvalidFilter(
+                "Expected at least one item.".to_string(),
+            ));
+        }
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return Err(LuxError::InvalidFilter(format!(
+                "False positive rate: {} is not between 0 and 1.",
+                false_positive_rate
+            )));
+        }
+
+        let items = expected_items as f64;
+        let bits = -items * false_positive_rate.ln() / (LN_2 * LN_2);
+        let size = ((bits / 8.0).ceil() as usize).clamp(1, MAX_FILTER_SIZE);
+        let hash_functions = ((size * 8) as f64 / items * LN_2).round() as u32;
+
+        Ok(Self {
+            bits: vec![0; size],
+            hash_functions: hash_functions.clamp(1, MAX_HASH_FUNCTIONS),
+        })
+    }
+
+    pub fn insert(&mut self, data: &[u8]) {
+        for index in self.indices(data) {
+            self.bits[index / 8] |= 1 << (index % 8);
+        }
+    }
+
+    pub fn contains(&self, data: &[u8]) -> bool {
+        self.indices(data)
+            .all(|index| self.bits[index / 8] & (1 << (index % 8)) != 0)
+    }
+
+    // Whether the transaction is relevant to the filter: its id, the address
+    // of one of its outputs, or the transaction one of its inputs spends from
+    // was inserted
+    pub fn matches_transaction(&self, transaction: &Transaction) -> bool {
+        self.contains(transaction.id().as_ref().as_slice())
+            || transaction
+                .outputs()
+                .iter()
+                .filter_map(TransactionOutput::to)
+                .any(|to| self.contains(to.as_str().as_bytes()))
+            || transaction
+                .inputs()
+                .iter()
+                .filter(|input| !input.is_coinbase())
+                .any(|input| self.contains(input.utxo_id().as_ref().as_slice()))
+    }
+
+    pub fn size(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn hash_functions(&self) -> u32 {
+        self.hash_functions
+    }
+
+    // Double hashing: the i-th bit is `h1 + i * h2`, both halves taken from a
+    // single SHA-256 of the data
+    fn indices(&self, data: &[u8]) -> impl Iterator<Item = usize> {
+        let hash = crypto::hash(data);
+        let mut h1 = [0; 8];
+        let mut h2 = [0; 8];
+        h1.copy_from_slice(&hash.as_slice()[..8]);
+        h2.copy_from_slice(&hash.as_slice()[8..16]);
+        let h1 = u64::from_le_bytes(h1);
+        let h2 = u64::from_le_bytes(h2);
+
+        let bits = (self.bits.len() * 8) as u64;
+        (0..self.hash_functions as u64)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bits) as usize)
+    }
+}
+
+// Filters come from untrusted peers, so deserialization enforces the same
+// bounds as `BloomFilter::new`
+#[derive(Deserialize)]
+struct BloomFilterData {
+    #[serde(with = "serde_bytes")]
+    bits: Vec<u8>,
+    hash_functions: u32,
+}
+
+impl TryFrom<BloomFilterData> for BloomFilter {
+    type Error = LuxError;
+
+    fn try_from(data: BloomFilterData) -> LuxResult<Self> {
+        if data.bits.is_empty() || data.bits.len() > MAX_FILTER_SIZE {
+            return Err(LuxError::InvalidFilter(format!(
+                "Filter is {} bytes, expected between 1 and {}.",
+                data.bits.len(),
+                MAX_FILTER_SIZE
+            )));
+        }
+        if data.hash_functions == 0 || data.hash_functions > MAX_HASH_FUNCTIONS {
+            return Err(LuxError::InvalidFilter(format!(
+                "Filter uses {} hash functions, expected between 1 and {}.",
+                data.hash_functions, MAX_HASH_FUNCTIONS
+            )));
+        }
+
+        Ok(Self {
+            bits: data.bits,
+            hash_functions: data.hash_functions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BloomFilter;
+    use crate::core::{
+        address::Address,
+        crypto,
+        error::LuxError,
+        luxcoin::Luxcoin,
+        transaction::{
+            OutputIndex, Transaction, TransactionId, TransactionInput, TransactionOutput,
+        },
+    };
+
+    fn transaction(to: &str) -> Transaction {
+        Transaction::new(
+            vec![TransactionInput::new(
+                TransactionId::new(crypto::hash(b"funding")),
+                OutputIndex::new(0),
+            )],
+            vec![TransactionOutput::new(Address::new(to.to_string()), Luxcoin::new(50)).unwrap()],
+            0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn inserted_items_are_contained() {
+        let mut filter = BloomFilter::new(100, 0.01).unwrap();
+        let items = (0..100u32).map(|i| i.to_le_bytes()).collect::<Vec<_>>();
+        items.iter().for_each(|item| filter.insert(item));
+
+        assert!(items.iter().all(|item| filter.contains(item)));
+        let false_positives = (100..10_100u32)
+            .filter(|i| filter.contains(&i.to_le_bytes()))
+            .count();
+        assert!(false_positives < 300, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn new_rejects_bad_parameters() {
+        assert!(matches!(
+            BloomFilter::new(0, 0.01),
+            Err(LuxError::InvalidFilter(_))
+        ));
+        assert!(matches!(
+            BloomFilter::new(10, 1.0),
+            Err(LuxError::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn matches_transactions_by_address_or_spent_output() {
+        let transaction = transaction("alice");
+
+        let mut by_address = BloomFilter::new(10, 0.0001).unwrap();
+        by_address.insert(b"alice");
+        assert!(by_address.matches_transaction(&transaction));
+
+        let mut by_input = BloomFilter::new(10, 0.0001).unwrap();
+        by_input.insert(crypto::hash(b"funding").as_slice());
+        assert!(by_input.matches_transaction(&transaction));
+
+        let mut unrelated = BloomFilter::new(10, 0.0001).unwrap();
+        unrelated.insert(b"bob");
+        assert!(!unrelated.matches_transaction(&transaction));
+    }
+
+    #[test]
+    fn serde_round_trip_and_bounds() {
+        let mut filter = BloomFilter::new(10, 0.01).unwrap();
+        filter.insert(b"alice");
+
+        let bytes = bincode::serialize(&filter).unwrap();
+        assert_eq!(bincode::deserialize::<BloomFilter>(&bytes).unwrap(), filter);
+
+        let json = serde_json::json!({ "bits": [], "hash_functions": 1 });
+        assert!(serde_json::from_value::<BloomFilter>(json).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+// Builders for values tests need but don't care about the details of. Random
+// values come from the OS, so every call returns something new.
+
+use std::sync::atomic::AtomicBool;
+
+use rand_core::{OsRng, RngCore};
+
+use crate::core::{
+    address::Address,
+    block::{Block, BlockHash, BlockHeader},
+    consensus::Network,
+    crypto::{self, MerkleTree, Sha256},
+    keys::KeyPair,
+    luxcoin::Luxcoin,
+    timestamp::Timestamp,
+    transaction::{OutputIndex, Transaction, TransactionId, TransactionInput, TransactionOutput},
+};
+
+pub fn random_sha256() -> Sha256 {
+    let mut bytes = [0; 32];
+    OsRng.fill_bytes(&mut bytes);
+    Sha256::new(bytes)
+}
+
+pub fn random_address() -> Address {
+    Address::new(random_sha256().to_string())
+}
+
+// The key pair of a named test user, the same on every call
+pub fn keypair(name: &str) -> KeyPair {
+    KeyPair::from_secret_bytes(crypto::hash(name.as_bytes()).as_slice()).unwrap()
+}
+
+// A mainnet address only `keypair(name)` can spend from
+pub fn address(name: &str) -> Address {
+    Address::from_pubkey(&keypair(name).public_key(), Network::Mainnet)
+}
+
+// Signs every input as `keypair(owner)`, the nth input spending `spent[n]`
+pub fn sign(
+    mut transaction: Transaction,
+    owner: &str,
+    spent: &[&TransactionOutput],
+) -> Transaction {
+    let keypair = keypair(owner);
+    for (i, prev_output) in spent.iter().enumerate() {
+        transaction.sign_input(i, &keypair, prev_output).unwrap();
+    }
+    transaction
+}
+
+// Spends output 0 of a random transaction, paying 50 units to a random address
+pub fn random_transaction() -> Transaction {
+    Transaction::new(
+        vec![TransactionInput::new(
+            TransactionId::new(random_sha256()),
+            OutputIndex::new(0),
+        )],
+        vec![TransactionOutput::new(random_address(), Luxcoin::new(50)).unwrap()],
+        0,
+    )
+    .unwrap()
+}
+
+// A coinbase and a couple of random transactions on top of `previous`, mined
+// at difficulty 0. The spent outputs don't exist, so the block passes
+// `Block::validate` but can't be applied to a UTXO set.
+pub fn random_block(previous: &BlockHash) -> Block {
+    let coinbase =
+        Transaction::new_coinbase(random_address(), Luxcoin::new(50), Luxcoin::ZERO, 0).unwrap();
+    let transactions = vec![coinbase, random_transaction(), random_transaction()];
+    mine_block(previous, transactions, 0, Timestamp::now())
+}
+
+// Builds the block and searches for a nonce meeting `difficulty`, so keep it
+// small
+pub fn mine_block(
+    previous: &BlockHash,
+    transactions: Vec<Transaction>,
+    difficulty: u32,
+    timestamp: Timestamp,
+) -> Block {
+    let merkle_root = MerkleTree::from(&transactions).root().clone();
+    let mut header = BlockHeader::new(*previous, merkle_root, timestamp, difficulty, 0);
+    header
+        .mine(
+            &crypto::target_hash(difficulty).unwrap(),
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+    Block::new(header, transactions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{address, keypair, random_block, random_sha256};
+    use crate::core::{block::BlockHash, consensus::ConsensusParams};
+
+    #[test]
+    fn random_blocks_are_valid_and_distinct() {
+        let first = random_block(&BlockHash::zero());
+        let second = random_block(first.id());
+
+        first.validate(&ConsensusParams::mainnet()).unwrap();
+        second.validate(&ConsensusParams::mainnet()).unwrap();
+        assert_eq!(second.header().previous_block_hash(), first.id());
+        assert_ne!(random_sha256(), random_sha256());
+    }
+
+    #[test]
+    fn named_keys_are_stable() {
+        assert_eq!(keypair("alice").public_key(), keypair("alice").public_key());
+        assert_ne!(address("alice"), address("bob"));
+        assert_eq!(
+            address("alice").pubkey_hash(),
+            Some(keypair("alice").public_key().hash160())
+        );
+    }
+}
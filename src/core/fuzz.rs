@@ -0,0 +1,35 @@
+// Entry points for the targets under `fuzz/`. Each feeds arbitrary bytes to a
+// parser, which must return an error or a value that survives a round trip,
+// and never panic.
+
+use crate::core::{
+    crypto::{self, Sha256},
+    net::message::Message,
+};
+
+pub fn from_hex(data: &[u8]) {
+    if let Ok(s) = std::str::from_utf8(data) {
+        if let Ok(hash) = crypto::from_hex(s) {
+            assert_eq!(crypto::from_hex(&hash.to_string()).unwrap(), hash);
+        }
+    }
+}
+
+// Both the binary and the human readable (hex string) encodings
+pub fn sha256(data: &[u8]) {
+    if let Ok(hash) = bincode::deserialize::<Sha256>(data) {
+        assert_eq!(&bincode::serialize(&hash).unwrap(), &data[..32]);
+    }
+    if let Ok(hash) = serde_json::from_slice::<Sha256>(data) {
+        let json = serde_json::to_vec(&hash).unwrap();
+        assert_eq!(serde_json::from_slice::<Sha256>(&json).unwrap(), hash);
+    }
+}
+
+pub fn message(data: &[u8]) {
+    if let Ok((message, size)) = Message::decode(data) {
+        assert!(size <= data.len());
+        let bytes = message.encode();
+        assert_eq!(Message::decode(&bytes).unwrap().1, bytes.len());
+    }
+}
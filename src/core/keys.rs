@@ -0,0 +1,171 @@
+use std::fmt::{self, Display};
+
+use k256::ecdsa::{
+    self,
+    signature::{Signature as _, Signer, Verifier},
+    SigningKey, VerifyingKey,
+};
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    crypto::{self, Sha256},
+    error::{LuxError, LuxResult},
+};
+
+// A secp256k1 ECDSA key pair
+pub struct KeyPair {
+    signing_key: SigningKey,
+}
+
+impl KeyPair {
+    pub fn generate() -> Self {
+        Self {
+            signing_key: SigningKey::random(&mut OsRng),
+        }
+    }
+
+    pub fn from_secret_bytes(bytes: &[u8]) -> LuxResult<Self> {
+        let signing_key =
+            SigningKey::from_bytes(bytes).map_err(|e| LuxError::InvalidKey(e.to_string()))?;
+        Ok(Self { signing_key })
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(self.signing_key.verifying_key())
+    }
+
+    // Signatures are deterministic (RFC 6979) over the bytes of `msg`
+    pub fn sign(&self, msg: &Sha256) -> Signature {
+        Signature(self.signing_key.sign(msg.as_slice()))
+    }
+}
+
+// Serialized as the hex encoded, compressed SEC1 point
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PublicKey(VerifyingKey);
+
+impl PublicKey {
+    pub fn from_bytes(bytes: &[u8]) -> LuxResult<Self> {
+        VerifyingKey::from_sec1_bytes(bytes)
+            .map(Self)
+            .map_err(|e| LuxError::InvalidKey(e.to_string()))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_bytes().to_vec()
+    }
+
+    pub fn hash(&self) -> Sha256 {
+        crypto::hash(&self.to_bytes())
+    }
+
+    // The key hash addresses pay to, see `Address::from_pubkey`
+    pub fn hash160(&self) -> [u8; 20] {
+        crypto::hash160(&self.to_bytes())
+    }
+}
+
+impl Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.to_bytes()))
+    }
+}
+
+impl TryFrom<String> for PublicKey {
+    type Error = LuxError;
+
+    fn try_from(value: String) -> LuxResult<Self> {
+        let bytes = hex::decode(&value).map_err(|e| LuxError::InvalidKey(e.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl From<PublicKey> for String {
+    fn from(key: PublicKey) -> Self {
+        key.to_string()
+    }
+}
+
+// Serialized as the hex encoded, fixed size (r, s) pair
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Signature(ecdsa::Signature);
+
+impl Signature {
+    pub fn from_bytes(bytes: &[u8]) -> LuxResult<Self> {
+        ecdsa::Signature::from_bytes(bytes)
+            .map(Self)
+            .map_err(|e| LuxError::InvalidKey(e.to_string()))
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+
+    pub fn verify(&self, msg: &Sha256, pubkey: &PublicKey) -> bool {
+        pubkey.0.verify(msg.as_slice(), &self.0).is_ok()
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.as_slice()))
+    }
+}
+
+impl TryFrom<String> for Signature {
+    type Error = LuxError;
+
+    fn try_from(value: String) -> LuxResult<Self> {
+        let bytes = hex::decode(&value).map_err(|e| LuxError::InvalidKey(e.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl From<Signature> for String {
+    fn from(signature: Signature) -> Self {
+        signature.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyPair, PublicKey, Signature};
+    use crate::core::crypto;
+
+    #[test]
+    fn sign_and_verify() {
+        let keypair = KeyPair::generate();
+        let msg = crypto::hash(b"hello world");
+
+        let signature = keypair.sign(&msg);
+        assert!(signature.verify(&msg, &keypair.public_key()));
+        assert!(!signature.verify(&crypto::hash(b"hello"), &keypair.public_key()));
+        assert!(!signature.verify(&msg, &KeyPair::generate().public_key()));
+    }
+
+    #[test]
+    fn keys_and_signatures_round_trip() {
+        let keypair = KeyPair::from_secret_bytes(&[7; 32]).unwrap();
+        let signature = keypair.sign(&crypto::hash(b"hello world"));
+
+        let pubkey = PublicKey::from_bytes(&keypair.public_key().to_bytes()).unwrap();
+        assert_eq!(pubkey, keypair.public_key());
+        assert_eq!(
+            Signature::from_bytes(signature.as_slice()).unwrap(),
+            signature
+        );
+
+        let json = serde_json::to_string(&signature).unwrap();
+        assert_eq!(serde_json::from_str::<Signature>(&json).unwrap(), signature);
+    }
+
+    #[test]
+    fn invalid_keys_are_rejected() {
+        assert!(KeyPair::from_secret_bytes(&[0; 32]).is_err());
+        assert!(PublicKey::from_bytes(&[2; 12]).is_err());
+        assert!(Signature::from_bytes(&[0; 64]).is_err());
+    }
+}
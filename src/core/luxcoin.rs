@@ -2,17 +2,149 @@ use std::{
     fmt::{self, Display},
     iter::Sum,
     ops::{Add, Sub},
+    str::FromStr,
 };
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+use crate::core::{
+    error::{LuxError, LuxResult},
+    transaction::{SIGNED_INPUT_SIZE, WITNESS_SCALE_FACTOR},
+};
+
+// Number of decimal places a LUX amount can be split into
+pub const DECIMALS: u32 = 8;
+// Smallest units in one LUX
+pub const UNITS_PER_LUX: i64 = 10_i64.pow(DECIMALS);
+
+// How `Luxcoin::format` renders an amount. The default matches `Display`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FormatOptions {
+    // Fractional digits to show, rounding half away from zero. None shows
+    // every significant digit and no trailing zeros.
+    pub decimals: Option<u32>,
+    // Whether to append " LUX"
+    pub suffix: bool,
+    // Inserted between groups of three digits of the whole part
+    pub thousands_separator: Option<char>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            decimals: None,
+            suffix: true,
+            thousands_separator: None,
+        }
+    }
+}
+
+// An amount counted in the smallest unit, 10^-8 LUX
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Luxcoin(i64);
 
 impl Luxcoin {
+    pub const ZERO: Self = Self(0);
+    pub const MAX: Self = Self(i64::MAX);
+
     pub fn new(amount: i64) -> Self {
         Self(amount)
     }
+
+    // Signed values are kept for fee and delta math, user supplied amounts
+    // should go through here
+    pub fn try_new(amount: i64) -> LuxResult<Self> {
+        if amount < 0 {
+            Err(LuxError::InvalidAmount(Self(amount)))
+        } else {
+            Ok(Self(amount))
+        }
+    }
+
+    pub fn as_i64(&self) -> i64 {
+        self.0
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.0 > 0
+    }
+
+    // Whether the amount is worth less than the fee for spending it later,
+    // i.e. the weight of a signed input at `fee_rate` units per weight unit
+    pub fn is_dust(&self, fee_rate: u64) -> bool {
+        let weight = SIGNED_INPUT_SIZE as i128 * WITNESS_SCALE_FACTOR as i128;
+        (self.0 as i128) < weight * fee_rate as i128
+    }
+
+    // Renders the amount in LUX, e.g. "1,234.5 LUX" or "0.50"
+    pub fn format(&self, options: FormatOptions) -> String {
+        let units = self.0.unsigned_abs();
+        let (whole, fraction) = match options.decimals.map(|d| d.min(DECIMALS)) {
+            Some(decimals) => {
+                let scale = 10_u64.pow(DECIMALS - decimals);
+                let rounded = units / scale + u64::from(units % scale >= scale.div_ceil(2));
+                let shown = 10_u64.pow(decimals);
+                let fraction = match decimals {
+                    0 => String::new(),
+                    _ => format!("{:0width$}", rounded % shown, width = decimals as usize),
+                };
+                (rounded / shown, fraction)
+            }
+            None => {
+                let fraction = format!(
+                    "{:0width$}",
+                    units % UNITS_PER_LUX as u64,
+                    width = DECIMALS as usize
+                );
+                (
+                    units / UNITS_PER_LUX as u64,
+                    fraction.trim_end_matches('0').to_string(),
+                )
+            }
+        };
+
+        let mut rendered = String::new();
+        if self.0 < 0 && (whole > 0 || fraction.bytes().any(|b| b != b'0')) {
+            rendered.push('-');
+        }
+        let digits = whole.to_string();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                rendered.extend(options.thousands_separator);
+            }
+            rendered.push(digit);
+        }
+        if !fraction.is_empty() {
+            rendered.push('.');
+            rendered.push_str(&fraction);
+        }
+        if options.suffix {
+            rendered.push_str(" LUX");
+        }
+        rendered
+    }
+
+    // Every decimal place, e.g. "1.50000000 LUX"
+    pub fn to_full_precision_string(self) -> String {
+        self.format(FormatOptions {
+            decimals: Some(DECIMALS),
+            ..FormatOptions::default()
+        })
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    // Returns None if the running total overflows at any point
+    pub fn checked_sum<I: IntoIterator<Item = Self>>(iter: I) -> Option<Self> {
+        iter.into_iter()
+            .try_fold(Self::ZERO, |sum, lc| sum.checked_add(lc))
+    }
 }
 
 impl Add for Luxcoin {
@@ -31,11 +163,11 @@ impl Sub for Luxcoin {
     }
 }
 
+// Saturates at the i64 bounds instead of wrapping. Use `Luxcoin::checked_sum`
+// where an overflow must be reported.
 impl Sum<Luxcoin> for Luxcoin {
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
-        let mut sum = Self::new(0);
-        iter.for_each(|lc| sum = sum + lc);
-        sum
+        iter.fold(Self::ZERO, |sum, lc| Self(sum.0.saturating_add(lc.0)))
     }
 }
 
@@ -51,8 +183,239 @@ impl From<i32> for Luxcoin {
     }
 }
 
+// Accepts a decimal amount of LUX with up to `DECIMALS` fractional digits and
+// an optional "LUX" suffix, e.g. "1.5", "-0.25" or "1.5 LUX"
+impl FromStr for Luxcoin {
+    type Err = LuxError;
+
+    fn from_str(s: &str) -> LuxResult<Self> {
+        let parse_error = |reason: &str| LuxError::AmountParse(format!("{} in: {}", reason, s));
+
+        let number = s.trim();
+        let number = number
+            .strip_suffix("LUX")
+            .map(str::trim_end)
+            .unwrap_or(number);
+        let (negative, digits) = match number.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, number.strip_prefix('+').unwrap_or(number)),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(parse_error("No digits"));
+        }
+        if !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+        {
+            return Err(parse_error("Invalid digit"));
+        }
+        if fraction.len() > DECIMALS as usize {
+            return Err(parse_error(&format!(
+                "More than {} decimal places",
+                DECIMALS
+            )));
+        }
+
+        // Only digits are left, so parsing can only fail by overflowing
+        let whole = match whole {
+            "" => 0,
+            whole => whole.parse::<i64>().map_err(|_| parse_error("Overflow"))?,
+        };
+        let fraction = format!("{:0<width$}", fraction, width = DECIMALS as usize)
+            .parse::<i64>()
+            .unwrap();
+        let units = whole
+            .checked_mul(UNITS_PER_LUX)
+            .and_then(|units| units.checked_add(fraction))
+            .ok_or_else(|| parse_error("Overflow"))?;
+
+        Ok(Self(if negative { -units } else { units }))
+    }
+}
+
+// Renders the decimal amount of LUX without trailing zeros, e.g. "1.5 LUX"
 impl Display for Luxcoin {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} LUX", self.0)
+        f.write_str(&self.format(FormatOptions::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FormatOptions, Luxcoin, UNITS_PER_LUX};
+    use crate::core::{
+        error::LuxError,
+        transaction::{SIGNED_INPUT_SIZE, WITNESS_SCALE_FACTOR},
+    };
+
+    #[test]
+    fn display_renders_decimals() {
+        assert_eq!(Luxcoin::new(50 * UNITS_PER_LUX).to_string(), "50 LUX");
+        assert_eq!(Luxcoin::new(150_000_000).to_string(), "1.5 LUX");
+        assert_eq!(Luxcoin::new(1).to_string(), "0.00000001 LUX");
+        assert_eq!(Luxcoin::new(-25_000_000).to_string(), "-0.25 LUX");
+        assert_eq!(Luxcoin::new(0).to_string(), "0 LUX");
+        assert_eq!(
+            Luxcoin::new(i64::MIN).to_string(),
+            "-92233720368.54775808 LUX"
+        );
+    }
+
+    #[test]
+    fn format_applies_options() {
+        let amount = Luxcoin::new(123_456_789_012_345);
+        assert_eq!(
+            amount.format(FormatOptions {
+                decimals: Some(2),
+                suffix: false,
+                thousands_separator: Some(','),
+            }),
+            "1,234,567.89"
+        );
+        assert_eq!(
+            Luxcoin::new(-99_999_999).format(FormatOptions {
+                decimals: Some(0),
+                ..FormatOptions::default()
+            }),
+            "-1 LUX"
+        );
+        assert_eq!(
+            Luxcoin::new(-1).format(FormatOptions {
+                decimals: Some(2),
+                ..FormatOptions::default()
+            }),
+            "0.00 LUX"
+        );
+        assert_eq!(
+            Luxcoin::new(100_000 * UNITS_PER_LUX).format(FormatOptions {
+                thousands_separator: Some('_'),
+                ..FormatOptions::default()
+            }),
+            "100_000 LUX"
+        );
+        assert_eq!(
+            Luxcoin::new(150_000_000).to_full_precision_string(),
+            "1.50000000 LUX"
+        );
+        assert_eq!(
+            Luxcoin::new(i64::MIN).to_full_precision_string(),
+            "-92233720368.54775808 LUX"
+        );
+    }
+
+    #[test]
+    fn from_str_parses_decimals() {
+        assert_eq!("1.5".parse::<Luxcoin>().unwrap(), Luxcoin::new(150_000_000));
+        assert_eq!(
+            "1.5 LUX".parse::<Luxcoin>().unwrap(),
+            Luxcoin::new(150_000_000)
+        );
+        assert_eq!(
+            "42".parse::<Luxcoin>().unwrap(),
+            Luxcoin::new(42 * UNITS_PER_LUX)
+        );
+        assert_eq!(".5".parse::<Luxcoin>().unwrap(), Luxcoin::new(50_000_000));
+        assert_eq!("-0.00000001".parse::<Luxcoin>().unwrap(), Luxcoin::new(-1));
+
+        for amount in [1, 150_000_000, -25_000_000, i64::MAX, -i64::MAX] {
+            let amount = Luxcoin::new(amount);
+            assert_eq!(amount.to_string().parse::<Luxcoin>().unwrap(), amount);
+        }
+    }
+
+    #[test]
+    fn from_str_rejects_malformed() {
+        for s in [
+            "", ".", "LUX", "1.2.3", "1,5", "abc", "1e8", "--1", " 1 . 5 ",
+        ] {
+            assert!(
+                matches!(s.parse::<Luxcoin>(), Err(LuxError::AmountParse(_))),
+                "{}",
+                s
+            );
+        }
+
+        assert_eq!(
+            "0.000000001".parse::<Luxcoin>().unwrap_err().to_string(),
+            "Failed to parse amount: More than 8 decimal places in: 0.000000001"
+        );
+        assert_eq!(
+            "92233720368.54775808"
+                .parse::<Luxcoin>()
+                .unwrap_err()
+                .to_string(),
+            "Failed to parse amount: Overflow in: 92233720368.54775808"
+        );
+        assert!(matches!(
+            "99999999999999999999".parse::<Luxcoin>(),
+            Err(LuxError::AmountParse(_))
+        ));
+    }
+
+    #[test]
+    fn try_new_rejects_negatives() {
+        assert_eq!(Luxcoin::try_new(0).unwrap(), Luxcoin::new(0));
+        assert_eq!(Luxcoin::try_new(42).unwrap(), Luxcoin::new(42));
+        assert!(matches!(
+            Luxcoin::try_new(-1),
+            Err(LuxError::InvalidAmount(amount)) if amount == Luxcoin::new(-1)
+        ));
+    }
+
+    #[test]
+    fn is_dust_compares_to_spending_cost() {
+        assert!(!Luxcoin::ZERO.is_dust(0));
+        assert!(Luxcoin::ZERO.is_dust(1));
+        assert!(Luxcoin::new(-1).is_dust(0));
+
+        let weight = SIGNED_INPUT_SIZE as u64 * WITNESS_SCALE_FACTOR;
+        let cost = weight as i64 * 10;
+        assert!(Luxcoin::new(cost - 1).is_dust(10));
+        assert!(!Luxcoin::new(cost).is_dust(10));
+        assert!(!Luxcoin::MAX.is_dust(i64::MAX as u64 / weight));
+        assert!(Luxcoin::MAX.is_dust(u64::MAX));
+    }
+
+    #[test]
+    fn checked_add_at_bounds() {
+        let max = Luxcoin::new(i64::MAX);
+        let min = Luxcoin::new(i64::MIN);
+
+        assert_eq!(max.checked_add(Luxcoin::new(0)), Some(max));
+        assert_eq!(max.checked_add(Luxcoin::new(1)), None);
+        assert_eq!(min.checked_add(Luxcoin::new(-1)), None);
+        assert_eq!(min.checked_add(max), Some(Luxcoin::new(-1)));
+    }
+
+    #[test]
+    fn checked_sub_at_bounds() {
+        let max = Luxcoin::new(i64::MAX);
+        let min = Luxcoin::new(i64::MIN);
+
+        assert_eq!(min.checked_sub(Luxcoin::new(1)), None);
+        assert_eq!(max.checked_sub(Luxcoin::new(-1)), None);
+        assert_eq!(max.checked_sub(max), Some(Luxcoin::new(0)));
+    }
+
+    #[test]
+    fn checked_sum_detects_overflow() {
+        let amounts = vec![Luxcoin::new(i64::MAX), Luxcoin::new(1), Luxcoin::new(-1)];
+        assert_eq!(Luxcoin::checked_sum(amounts), None);
+
+        let amounts = vec![Luxcoin::new(1), Luxcoin::new(2), Luxcoin::new(3)];
+        assert_eq!(Luxcoin::checked_sum(amounts), Some(Luxcoin::new(6)));
+        assert_eq!(Luxcoin::checked_sum(vec![]), Some(Luxcoin::new(0)));
+    }
+
+    #[test]
+    fn sum_saturates() {
+        let amounts = vec![Luxcoin::new(i64::MAX), Luxcoin::new(1)];
+        assert_eq!(amounts.into_iter().sum::<Luxcoin>(), Luxcoin::new(i64::MAX));
+
+        let amounts = vec![Luxcoin::new(i64::MIN), Luxcoin::new(-1)];
+        assert_eq!(amounts.into_iter().sum::<Luxcoin>(), Luxcoin::new(i64::MIN));
     }
 }
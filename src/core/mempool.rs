@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+
+use crate::core::{
+    block::Block,
+    error::{LuxError, LuxResult},
+    fee_rate::FeeRate,
+    luxcoin::Luxcoin,
+    transaction::{Transaction, TransactionId},
+    utxo::{OutPoint, UtxoSet},
+};
+
+struct MempoolEntry {
+    transaction: Transaction,
+    fee: Luxcoin,
+}
+
+impl MempoolEntry {
+    // Fee per weight unit
+    fn fee_rate(&self) -> FeeRate {
+        FeeRate::new(self.fee, self.transaction.weight() as usize)
+    }
+}
+
+// Local rules for what the mempool accepts, as opposed to consensus rules
+#[derive(Clone, Debug)]
+pub struct MempoolPolicy {
+    // Whether a transaction may replace pending transactions spending the same
+    // outputs by paying more for them
+    pub replace_by_fee: bool,
+    // Fee a replacement pays per weight unit on top of the fees it displaces,
+    // so that relaying it isn't free
+    pub incremental_fee_rate: u64,
+}
+
+impl Default for MempoolPolicy {
+    fn default() -> Self {
+        Self {
+            replace_by_fee: false,
+            incremental_fee_rate: 1,
+        }
+    }
+}
+
+// Transactions waiting to be included in a block. Every entry spends outputs
+// from the UTXO set directly, so chains of unconfirmed transactions aren't
+// accepted.
+#[derive(Default)]
+pub struct Mempool {
+    entries: HashMap<TransactionId, MempoolEntry>,
+    // The pending transaction spending each outpoint
+    spends: HashMap<OutPoint, TransactionId>,
+    policy: MempoolPolicy,
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_policy(policy: MempoolPolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    pub fn policy(&self) -> &MempoolPolicy {
+        &self.policy
+    }
+
+    pub fn get(&self, id: &TransactionId) -> Option<&Transaction> {
+        self.entries.get(id).map(|entry| &entry.transaction)
+    }
+
+    pub fn contains(&self, id: &TransactionId) -> bool {
+        self.entries.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Accepts a transaction whose inputs are all unspent in `utxos`, signed by
+    // their owners and not already claimed by another pending transaction.
+    // With replace by fee enabled, a transaction conflicting with pending ones
+    // evicts them instead if it pays enough more, see
+    // `Mempool::validate_replacement`.
+    pub fn add(&mut self, transaction: Transaction, utxos: &UtxoSet) -> LuxResult<()> {
+        if transaction.is_coinbase() {
+            return Err(LuxError::InvalidTransaction(format!(
+                "Coinbase transaction: {} can't enter the mempool.",
+                transaction.id()
+            )));
+        }
+        if self.contains(transaction.id()) {
+            return Err(LuxError::InvalidTransaction(format!(
+                "Transaction: {} is already in the mempool.",
+                transaction.id()
+            )));
+        }
+
+        let mut outpoints = vec![];
+        let mut conflicts = vec![];
+        for input in transaction.inputs() {
+            let outpoint = (*input.utxo_id(), *input.output_index());
+            let conflict = self.spends.get(&outpoint);
+            if outpoints.contains(&outpoint) || (conflict.is_some() && !self.policy.replace_by_fee)
+            {
+                return Err(LuxError::DoubleSpend {
+                    utxo: outpoint.0,
+                    index: outpoint.1,
+                });
+            }
+            if let Some(conflict) = conflict.filter(|id| !conflicts.contains(*id)) {
+                conflicts.push(*conflict);
+            }
+            outpoints.push(outpoint);
+        }
+        let fee = transaction.fee(utxos)?;
+        transaction.verify_inputs_with(|utxo_id, index| utxos.get(utxo_id, index))?;
+
+        self.validate_replacement(&conflicts, &transaction, fee)?;
+        conflicts.iter().for_each(|conflict| {
+            self.remove(conflict);
+        });
+
+        let id = *transaction.id();
+        outpoints.into_iter().for_each(|outpoint| {
+            self.spends.insert(outpoint, id);
+        });
+        self.entries.insert(id, MempoolEntry { transaction, fee });
+        Ok(())
+    }
+
+    // A replacement must pay at least the fees of every transaction it
+    // conflicts with plus `incremental_fee_rate` for its own weight, and a
+    // higher fee per weight unit than each of them. Pending transactions
+    // never spend each other, so the conflicts have no descendants to account
+    // for.
+    fn validate_replacement(
+        &self,
+        conflicts: &[TransactionId],
+        transaction: &Transaction,
+        fee: Luxcoin,
+    ) -> LuxResult<()> {
+        let rate = FeeRate::new(fee, transaction.weight() as usize);
+        let mut replaced_fees = Luxcoin::ZERO;
+        for conflict in conflicts {
+            let entry = &self.entries[conflict];
+            if rate <= entry.fee_rate() {
+                return Err(LuxError::ReplacementRejected(format!(
+                    "Fee rate: {} doesn't exceed: {} of transaction: {}.",
+                    rate,
+                    entry.fee_rate(),
+                    conflict
+                )));
+            }
+            replaced_fees = replaced_fees.checked_add(entry.fee).ok_or_else(|| {
+                LuxError::ReplacementRejected("Replaced fees overflow.".to_string())
+            })?;
+        }
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+
+        let increment = (self.policy.incremental_fee_rate as i128 * transaction.weight() as i128)
+            .min(i64::MAX as i128) as i64;
+        let required = replaced_fees
+            .checked_add(Luxcoin::new(increment))
+            .unwrap_or(Luxcoin::MAX);
+        if fee < required {
+            return Err(LuxError::ReplacementRejected(format!(
+                "Fee: {} is less than the required: {}.",
+                fee, required
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &TransactionId) -> Option<Transaction> {
+        let entry = self.entries.remove(id)?;
+        entry.transaction.inputs().iter().for_each(|input| {
+            self.spends
+                .remove(&(*input.utxo_id(), *input.output_index()));
+        });
+        Some(entry.transaction)
+    }
+
+    // Up to `max_count` transactions, highest fee per weight unit first
+    pub fn select_for_block(&self, max_count: usize) -> Vec<&Transaction> {
+        let mut entries = self.entries.values().collect::<Vec<&MempoolEntry>>();
+        entries.sort_by(|lhs, rhs| {
+            rhs.fee_rate().cmp(&lhs.fee_rate()).then_with(|| {
+                lhs.transaction
+                    .id()
+                    .as_ref()
+                    .cmp(rhs.transaction.id().as_ref())
+            })
+        });
+
+        entries
+            .into_iter()
+            .take(max_count)
+            .map(|entry| &entry.transaction)
+            .collect()
+    }
+
+    // Drops the transactions confirmed by `block`, then evicts any whose inputs
+    // are no longer unspent in `utxos`, which must already include the block
+    pub fn apply_block(&mut self, block: &Block, utxos: &UtxoSet) {
+        block.transactions().iter().for_each(|transaction| {
+            self.remove(transaction.id());
+        });
+
+        let invalid = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| {
+                entry
+                    .transaction
+                    .inputs()
+                    .iter()
+                    .any(|input| !utxos.contains(input.utxo_id(), input.output_index()))
+            })
+            .map(|(id, _)| *id)
+            .collect::<Vec<TransactionId>>();
+        invalid.iter().for_each(|id| {
+            self.remove(id);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mempool, MempoolPolicy};
+    use crate::core::{
+        block::{Block, BlockHash, BlockHeader},
+        crypto::MerkleTree,
+        error::LuxError,
+        fixtures,
+        luxcoin::Luxcoin,
+        timestamp::Timestamp,
+        transaction::{OutputIndex, Transaction, TransactionInput, TransactionOutput},
+        utxo::UtxoSet,
+    };
+
+    fn output(to: &str, amount: i64) -> TransactionOutput {
+        TransactionOutput::new(fixtures::address(to), Luxcoin::new(amount)).unwrap()
+    }
+
+    fn coinbase(outputs: Vec<TransactionOutput>) -> Transaction {
+        Transaction::new(vec![TransactionInput::new_coinbase(0)], outputs, 0).unwrap()
+    }
+
+    // Spends output `index` of `from`, signed by alice, who every funded
+    // output pays to
+    fn spend(from: &Transaction, index: i32, outputs: Vec<TransactionOutput>) -> Transaction {
+        let transaction = Transaction::new(
+            vec![TransactionInput::new(*from.id(), OutputIndex::new(index))],
+            outputs,
+            0,
+        )
+        .unwrap();
+        let spent = from
+            .outputs()
+            .get(index as usize)
+            .into_iter()
+            .collect::<Vec<_>>();
+        fixtures::sign(transaction, "alice", &spent)
+    }
+
+    fn block(previous: &BlockHash, transactions: Vec<Transaction>) -> Block {
+        let merkle_root = MerkleTree::from(&transactions).as_ref().clone();
+        let header = BlockHeader::new(*previous, merkle_root, Timestamp::new(0), 0, 0);
+        Block::new(header, transactions)
+    }
+
+    // A UTXO set holding three 100 LUX outputs from a single transaction
+    fn funded() -> (Transaction, UtxoSet) {
+        funded_with(100)
+    }
+
+    // Three confirmed outputs of `amount` each
+    fn funded_with(amount: i64) -> (Transaction, UtxoSet) {
+        let reward = coinbase(vec![output("alice", 3 * amount)]);
+        let split = spend(
+            &reward,
+            0,
+            vec![
+                output("alice", amount),
+                output("alice", amount),
+                output("alice", amount),
+            ],
+        );
+        let mut utxos = UtxoSet::new();
+        utxos
+            .apply_block(&block(&BlockHash::zero(), vec![reward, split.clone()]))
+            .unwrap();
+        (split, utxos)
+    }
+
+    #[test]
+    fn rejects_double_spends() {
+        let (reward, utxos) = funded();
+        let mut mempool = Mempool::new();
+        let first = spend(&reward, 0, vec![output("bob", 90)]);
+        mempool.add(first.clone(), &utxos).unwrap();
+
+        assert!(matches!(
+            mempool.add(first.clone(), &utxos),
+            Err(LuxError::InvalidTransaction(_))
+        ));
+        assert!(matches!(
+            mempool.add(spend(&reward, 0, vec![output("carol", 80)]), &utxos),
+            Err(LuxError::DoubleSpend { .. })
+        ));
+        assert!(matches!(
+            mempool.add(spend(&reward, 7, vec![output("carol", 80)]), &utxos),
+            Err(LuxError::MissingOutput { .. })
+        ));
+        assert!(matches!(
+            mempool.add(coinbase(vec![output("carol", 1)]), &utxos),
+            Err(LuxError::InvalidTransaction(_))
+        ));
+
+        mempool.remove(first.id()).unwrap();
+        mempool
+            .add(spend(&reward, 0, vec![output("carol", 80)]), &utxos)
+            .unwrap();
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn rejects_spends_not_signed_by_the_owner() {
+        let (reward, utxos) = funded();
+        let mut mempool = Mempool::new();
+        let unsigned = Transaction::new(
+            vec![TransactionInput::new(*reward.id(), OutputIndex::new(0))],
+            vec![output("mallory", 90)],
+            0,
+        )
+        .unwrap();
+        assert!(matches!(
+            mempool.add(unsigned.clone(), &utxos),
+            Err(LuxError::InvalidSignature { .. })
+        ));
+
+        let stolen = fixtures::sign(unsigned, "mallory", &[&reward.outputs()[0]]);
+        assert!(matches!(
+            mempool.add(stolen, &utxos),
+            Err(LuxError::InvalidSignature { .. })
+        ));
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn replace_by_fee_evicts_conflicts() {
+        // Large enough for the replacement to cover its weight
+        let (reward, utxos) = funded_with(10_000);
+        let mut mempool = Mempool::with_policy(MempoolPolicy {
+            replace_by_fee: true,
+            ..MempoolPolicy::default()
+        });
+        let stuck = spend(&reward, 0, vec![output("bob", 10_000)]);
+        let other = spend(&reward, 1, vec![output("bob", 9_999)]);
+        mempool.add(stuck.clone(), &utxos).unwrap();
+        mempool.add(other.clone(), &utxos).unwrap();
+
+        // Pays a fee, but not enough to cover its own weight on top of nothing
+        let cheap = spend(&reward, 0, vec![output("bob", 9_999)]);
+        assert!(matches!(
+            mempool.add(cheap, &utxos),
+            Err(LuxError::ReplacementRejected(_))
+        ));
+
+        let replacement = Transaction::new(
+            vec![
+                TransactionInput::new(*reward.id(), OutputIndex::new(0)),
+                TransactionInput::new(*reward.id(), OutputIndex::new(1)),
+            ],
+            vec![output("bob", 1)],
+            0,
+        )
+        .unwrap();
+        let spent = [&reward.outputs()[0], &reward.outputs()[1]];
+        let replacement = fixtures::sign(replacement, "alice", &spent);
+        mempool.add(replacement.clone(), &utxos).unwrap();
+        assert_eq!(mempool.len(), 1);
+        assert!(mempool.contains(replacement.id()));
+        assert!(!mempool.contains(stuck.id()));
+        assert!(!mempool.contains(other.id()));
+
+        // A replacement paying less than the transaction it replaces
+        let lower = spend(&reward, 1, vec![output("bob", 50)]);
+        assert!(matches!(
+            mempool.add(lower, &utxos),
+            Err(LuxError::ReplacementRejected(_))
+        ));
+    }
+
+    #[test]
+    fn selects_by_fee_rate() {
+        let (reward, utxos) = funded();
+        let mut mempool = Mempool::new();
+        let low = spend(&reward, 0, vec![output("bob", 99)]);
+        let high = spend(&reward, 1, vec![output("bob", 50)]);
+        // Pays more in total than `low` but spreads it over a larger transaction
+        let mid = spend(
+            &reward,
+            2,
+            vec![output("bob", 30), output("carol", 30), output("dave", 30)],
+        );
+        for transaction in [&low, &high, &mid] {
+            mempool.add(transaction.clone(), &utxos).unwrap();
+        }
+
+        let selected = mempool
+            .select_for_block(3)
+            .iter()
+            .map(|transaction| *transaction.id())
+            .collect::<Vec<_>>();
+        assert_eq!(selected, vec![*high.id(), *mid.id(), *low.id()]);
+        assert_eq!(mempool.select_for_block(1).len(), 1);
+    }
+
+    #[test]
+    fn apply_block_evicts_confirmed_and_conflicting() {
+        let (reward, mut utxos) = funded();
+        let mut mempool = Mempool::new();
+        let confirmed = spend(&reward, 0, vec![output("bob", 90)]);
+        let conflicting = spend(&reward, 1, vec![output("bob", 90)]);
+        let untouched = spend(&reward, 2, vec![output("bob", 90)]);
+        for transaction in [&confirmed, &conflicting, &untouched] {
+            mempool.add(transaction.clone(), &utxos).unwrap();
+        }
+
+        // A competing spend of output 1 made it into the block instead
+        let next = block(
+            &BlockHash::zero(),
+            vec![
+                coinbase(vec![output("miner", 50)]),
+                confirmed.clone(),
+                spend(&reward, 1, vec![output("carol", 95)]),
+            ],
+        );
+        utxos.apply_block(&next).unwrap();
+        mempool.apply_block(&next, &utxos);
+
+        assert_eq!(mempool.len(), 1);
+        assert!(mempool.contains(untouched.id()));
+        assert!(!mempool.contains(conflicting.id()));
+        mempool
+            .add(spend(&reward, 1, vec![output("dave", 1)]), &utxos)
+            .unwrap_err();
+    }
+}
@@ -1,7 +1,27 @@
 mod address;
+mod base58;
 mod block;
+mod block_template;
 mod blockchain;
+mod coin_selection;
+mod consensus;
 mod crypto;
+mod difficulty;
+mod economics;
 mod error;
+mod fee_rate;
+mod filter;
+#[cfg(test)]
+mod fixtures;
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
+mod keys;
 mod luxcoin;
+mod mempool;
+mod net;
+mod rpc;
+mod store;
+mod timestamp;
 mod transaction;
+mod utxo;
+mod wallet;
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    block::{Block, BlockHash},
+    crypto,
+    error::{LuxError, LuxResult},
+    transaction::{Transaction, TransactionId},
+};
+
+// Marks the start of every frame, so peers on another network or protocol are
+// told apart immediately
+pub const MAGIC: [u8; 4] = [0x4c, 0x55, 0x58, 0x01];
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// Magic, payload length and checksum
+pub const FRAME_HEADER_SIZE: usize = 12;
+
+// Larger payloads are rejected before anything is allocated for them
+pub const MAX_PAYLOAD_SIZE: usize = 4_000_000;
+
+// Something a peer can announce or ask for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Inventory {
+    Block(BlockHash),
+    Transaction(TransactionId),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Message {
+    // First message on a connection, announcing the protocol version and the
+    // height of the sender's chain
+    Version { version: u32, height: u32 },
+    // Acknowledges the peer's `Version`, completing its side of the handshake
+    Verack,
+    // Announces blocks and transactions the sender has
+    Inv(Vec<Inventory>),
+    // Asks for the full blocks and transactions behind an announcement
+    GetData(Vec<Inventory>),
+    Block(Block),
+    Tx(Transaction),
+    Ping(u64),
+    Pong(u64),
+}
+
+impl Message {
+    // Frame layout: magic, payload length as a little endian u32, the first 4
+    // bytes of the payload's double SHA-256, then the bincode encoded message
+    pub fn encode(&self) -> Vec<u8> {
+        // Every variant has an infallible Serialize impl
+        let payload = bincode::serialize(self).expect("messages always serialize");
+
+        let mut bytes = Vec::with_capacity(FRAME_HEADER_SIZE + payload.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&checksum(&payload));
+        bytes.extend_from_slice(&payload);
+        bytes
+    }
+
+    // Decodes the frame at the start of `buf`, returning the message and the
+    // number of bytes it took up. Fails with `LuxError::IncompleteMessage` when
+    // the frame hasn't been fully received yet.
+    pub fn decode(buf: &[u8]) -> LuxResult<(Message, usize)> {
+        if buf.len() < FRAME_HEADER_SIZE {
+            return Err(LuxError::IncompleteMessage {
+                needed: FRAME_HEADER_SIZE,
+                available: buf.len(),
+            });
+        }
+
+        if buf[..4] != MAGIC {
+            return Err(LuxError::InvalidMessage(format!(
+                "Unknown magic: {}.",
+                crypto::as_hex(&buf[..4])
+            )));
+        }
+
+        let length = u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]) as usize;
+        if length > MAX_PAYLOAD_SIZE {
+            return Err(LuxError::InvalidMessage(format!(
+                "Payload is {} bytes, exceeding the maximum of {}.",
+                length, MAX_PAYLOAD_SIZE
+            )));
+        }
+
+        let size = FRAME_HEADER_SIZE + length;
+        if buf.len() < size {
+            return Err(LuxError::IncompleteMessage {
+                needed: size,
+                available: buf.len(),
+            });
+        }
+
+        let payload = &buf[FRAME_HEADER_SIZE..size];
+        if buf[8..12] != checksum(payload) {
+            return Err(LuxError::InvalidMessage("Checksum mismatch.".to_string()));
+        }
+
+        let message =
+            bincode::deserialize(payload).map_err(|e| LuxError::InvalidMessage(e.to_string()))?;
+        Ok((message, size))
+    }
+}
+
+fn checksum(payload: &[u8]) -> [u8; 4] {
+    let mut checksum = [0; 4];
+    checksum.copy_from_slice(&crypto::hash256(payload).as_slice()[..4]);
+    checksum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Inventory, Message, FRAME_HEADER_SIZE, MAGIC};
+    use crate::core::{
+        address::Address, block::Block, consensus::ConsensusParams, error::LuxError,
+        luxcoin::Luxcoin,
+    };
+
+    fn block() -> Block {
+        Block::genesis(
+            Address::new("genesis".to_string()),
+            Luxcoin::new(50),
+            0,
+            &ConsensusParams::mainnet(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn round_trips_frames() {
+        let block = block();
+        let messages = vec![
+            Message::Version {
+                version: 1,
+                height: 7,
+            },
+            Message::Inv(vec![Inventory::Block(*block.id())]),
+            Message::Block(block),
+            Message::Ping(42),
+        ];
+
+        for message in messages {
+            let bytes = message.encode();
+            assert_eq!(&bytes[..4], &MAGIC);
+
+            let (decoded, size) = Message::decode(&bytes).unwrap();
+            assert_eq!(size, bytes.len());
+            assert_eq!(decoded.encode(), bytes);
+        }
+    }
+
+    #[test]
+    fn decode_consumes_one_frame() {
+        let mut bytes = Message::Ping(1).encode();
+        let first = bytes.len();
+        bytes.extend(Message::Pong(1).encode());
+
+        let (message, size) = Message::decode(&bytes).unwrap();
+        assert!(matches!(message, Message::Ping(1)));
+        assert_eq!(size, first);
+        assert!(matches!(
+            Message::decode(&bytes[size..]).unwrap().0,
+            Message::Pong(1)
+        ));
+    }
+
+    #[test]
+    fn decode_rejects_bad_frames() {
+        let bytes = Message::Ping(1).encode();
+
+        assert!(matches!(
+            Message::decode(&bytes[..bytes.len() - 1]),
+            Err(LuxError::IncompleteMessage { .. })
+        ));
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] ^= 1;
+        assert!(matches!(
+            Message::decode(&bad_magic),
+            Err(LuxError::InvalidMessage(_))
+        ));
+
+        let mut bad_payload = bytes;
+        bad_payload[FRAME_HEADER_SIZE] ^= 1;
+        assert!(matches!(
+            Message::decode(&bad_payload),
+            Err(LuxError::InvalidMessage(_))
+        ));
+    }
+}
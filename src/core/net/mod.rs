@@ -0,0 +1,3 @@
+pub mod message;
+#[cfg(feature = "net")]
+pub mod peer;
@@ -0,0 +1,209 @@
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, ToSocketAddrs},
+    time,
+};
+
+use crate::core::{
+    error::{LuxError, LuxResult},
+    net::message::{Message, PROTOCOL_VERSION},
+};
+
+// How long a peer may stay silent before the connection is given up on
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+// A connection to another node that has completed the Version/Verack
+// handshake. Frames are read into `buffer` until a whole message has arrived.
+pub struct Peer {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    idle_timeout: Duration,
+    version: u32,
+    height: u32,
+}
+
+impl Peer {
+    // Connects to `address` and performs the handshake, announcing `height`
+    pub async fn connect<A: ToSocketAddrs>(address: A, height: u32) -> LuxResult<Self> {
+        let stream = TcpStream::connect(address)
+            .await
+            .map_err(connection_error)?;
+        Self::handshake(stream, height).await
+    }
+
+    // Both sides send their `Version` and answer the other's with a `Verack`,
+    // so the same steps work for inbound and outbound connections
+    pub async fn handshake(stream: TcpStream, height: u32) -> LuxResult<Self> {
+        Self::handshake_with_timeout(stream, height, IDLE_TIMEOUT).await
+    }
+
+    pub async fn handshake_with_timeout(
+        stream: TcpStream,
+        height: u32,
+        idle_timeout: Duration,
+    ) -> LuxResult<Self> {
+        let mut peer = Self {
+            stream,
+            buffer: vec![],
+            idle_timeout,
+            version: 0,
+            height: 0,
+        };
+        peer.send(Message::Version {
+            version: PROTOCOL_VERSION,
+            height,
+        })
+        .await?;
+
+        let mut version = None;
+        let mut acknowledged = false;
+        while version.is_none() || !acknowledged {
+            match peer.recv().await? {
+                Message::Version { .. } if version.is_some() => {
+                    return Err(LuxError::Connection(
+                        "Peer sent its version twice.".to_string(),
+                    ))
+                }
+                Message::Version { version: v, .. } if v != PROTOCOL_VERSION => {
+                    return Err(LuxError::Connection(format!(
+                        "Unsupported protocol version: {}.",
+                        v
+                    )))
+                }
+                Message::Version { version: v, height } => {
+                    version = Some(v);
+                    peer.height = height;
+                    peer.send(Message::Verack).await?;
+                }
+                Message::Verack => acknowledged = true,
+                message => {
+                    return Err(LuxError::Connection(format!(
+                        "Expected a handshake message but got: {:?}.",
+                        message
+                    )))
+                }
+            }
+        }
+
+        peer.version = version.unwrap_or_default();
+        Ok(peer)
+    }
+
+    // Protocol version the peer announced
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    // Chain height the peer announced during the handshake
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub async fn send(&mut self, message: Message) -> LuxResult<()> {
+        self.stream
+            .write_all(&message.encode())
+            .await
+            .map_err(connection_error)
+    }
+
+    // Waits for the next message. Pings are answered with a matching pong
+    // rather than returned, and a peer that sends nothing for the idle timeout
+    // fails with `LuxError::PeerTimeout`.
+    pub async fn recv(&mut self) -> LuxResult<Message> {
+        loop {
+            match Message::decode(&self.buffer) {
+                Ok((Message::Ping(nonce), size)) => {
+                    self.buffer.drain(..size);
+                    self.send(Message::Pong(nonce)).await?;
+                }
+                Ok((message, size)) => {
+                    self.buffer.drain(..size);
+                    return Ok(message);
+                }
+                Err(LuxError::IncompleteMessage { .. }) => self.fill_buffer().await?,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn fill_buffer(&mut self) -> LuxResult<()> {
+        let read = time::timeout(self.idle_timeout, self.stream.read_buf(&mut self.buffer))
+            .await
+            .map_err(|_| LuxError::PeerTimeout(self.idle_timeout))?
+            .map_err(connection_error)?;
+        if read == 0 {
+            return Err(LuxError::Connection(
+                "Peer closed the connection.".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn connection_error(error: std::io::Error) -> LuxError {
+    LuxError::Connection(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    use super::Peer;
+    use crate::core::{error::LuxError, net::message::Message};
+
+    async fn connected_pair() -> (Peer, Peer) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let inbound = async {
+            let (stream, _) = listener.accept().await.unwrap();
+            Peer::handshake(stream, 5).await.unwrap()
+        };
+        let outbound = async { Peer::connect(address, 3).await.unwrap() };
+        tokio::join!(inbound, outbound)
+    }
+
+    #[tokio::test]
+    async fn handshake_exchanges_heights() {
+        let (inbound, outbound) = connected_pair().await;
+        assert_eq!(inbound.height(), 3);
+        assert_eq!(outbound.height(), 5);
+    }
+
+    #[tokio::test]
+    async fn answers_pings_automatically() {
+        let (mut inbound, mut outbound) = connected_pair().await;
+
+        outbound.send(Message::Ping(7)).await.unwrap();
+        outbound.send(Message::Inv(vec![])).await.unwrap();
+
+        let (received, pong) = tokio::join!(inbound.recv(), outbound.recv());
+        assert!(matches!(received.unwrap(), Message::Inv(items) if items.is_empty()));
+        assert!(matches!(pong.unwrap(), Message::Pong(7)));
+    }
+
+    #[tokio::test]
+    async fn idle_peers_time_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let silent = async {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            // Keep the connection open without ever answering
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            stream.shutdown().await.unwrap();
+        };
+        let outbound = async {
+            let stream = tokio::net::TcpStream::connect(address).await.unwrap();
+            Peer::handshake_with_timeout(stream, 0, Duration::from_millis(50)).await
+        };
+
+        let (_, result) = tokio::join!(silent, outbound);
+        assert!(matches!(result, Err(LuxError::PeerTimeout(_))));
+    }
+}
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    address::Address,
+    block::{Block, BlockHash},
+    blockchain::Blockchain,
+    error::LuxResult,
+    luxcoin::Luxcoin,
+    mempool::Mempool,
+    store::BlockStore,
+    transaction::{Transaction, TransactionId},
+    wallet::Wallet,
+};
+
+// Operations a node exposes to its clients. Encoded as
+// `{"method": ..., "params": ...}`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Request {
+    GetBlock(BlockHash),
+    // Searches the canonical chain, then the mempool
+    GetTransaction(TransactionId),
+    SubmitTransaction(Transaction),
+    GetTip,
+    GetBalance(Address),
+}
+
+// Lookups that find nothing answer with `null` rather than an error. Failures
+// carry the rendered `LuxError`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    Block(Option<Block>),
+    Transaction(Option<Transaction>),
+    Submitted(TransactionId),
+    Tip { hash: BlockHash, height: u32 },
+    Balance(Luxcoin),
+    Error(String),
+}
+
+// Answers a request against the node's chain and mempool
+pub fn dispatch<S: BlockStore>(
+    request: Request,
+    chain: &mut Blockchain<S>,
+    mempool: &mut Mempool,
+) -> Response {
+    match handle(request, chain, mempool) {
+        Ok(response) => response,
+        Err(e) => Response::Error(e.to_string()),
+    }
+}
+
+fn handle<S: BlockStore>(
+    request: Request,
+    chain: &mut Blockchain<S>,
+    mempool: &mut Mempool,
+) -> LuxResult<Response> {
+    let response = match request {
+        Request::GetBlock(hash) => Response::Block(chain.get_block(&hash)?.cloned()),
+        Request::GetTransaction(id) => {
+            // Transactions in pruned blocks can't be found anymore
+            let confirmed = (0..=chain.height())
+                .filter_map(|height| chain.get_block_at(height).ok().flatten())
+                .flat_map(Block::transactions)
+                .find(|transaction| transaction.id() == &id);
+            let transaction = confirmed.or_else(|| mempool.get(&id));
+            Response::Transaction(transaction.cloned())
+        }
+        Request::SubmitTransaction(transaction) => {
+            let id = *transaction.id();
+            mempool.add(transaction, chain.utxos())?;
+            Response::Submitted(id)
+        }
+        Request::GetTip => Response::Tip {
+            hash: *chain.tip().id(),
+            height: chain.height(),
+        },
+        Request::GetBalance(address) => {
+            Response::Balance(Wallet::new(address).balance(chain.utxos()))
+        }
+    };
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dispatch, Request, Response};
+    use crate::core::{
+        address::Address,
+        block::Block,
+        blockchain::Blockchain,
+        consensus::ConsensusParams,
+        fixtures,
+        luxcoin::Luxcoin,
+        mempool::Mempool,
+        transaction::{OutputIndex, Transaction, TransactionInput, TransactionOutput},
+    };
+
+    fn chain() -> Blockchain {
+        let genesis = Block::genesis(
+            fixtures::address("genesis"),
+            Luxcoin::new(50),
+            0,
+            &ConsensusParams::mainnet(),
+        )
+        .unwrap();
+        Blockchain::new(genesis, ConsensusParams::mainnet()).unwrap()
+    }
+
+    #[test]
+    fn request_wire_format() {
+        let json = serde_json::to_string(&Request::GetTip).unwrap();
+        assert_eq!(json, r#"{"method":"get_tip"}"#);
+
+        let request: Request =
+            serde_json::from_str(r#"{"method":"get_balance","params":"genesis"}"#).unwrap();
+        assert!(matches!(request, Request::GetBalance(address) if address.as_str() == "genesis"));
+    }
+
+    #[test]
+    fn answers_queries() {
+        let mut chain = chain();
+        let mut mempool = Mempool::new();
+        let genesis = chain.tip().clone();
+
+        match dispatch(Request::GetTip, &mut chain, &mut mempool) {
+            Response::Tip { hash, height } => {
+                assert_eq!(&hash, genesis.id());
+                assert_eq!(height, 0);
+            }
+            response => panic!("unexpected response: {:?}", response),
+        }
+
+        let coinbase = genesis.transactions()[0].clone();
+        match dispatch(
+            Request::GetTransaction(*coinbase.id()),
+            &mut chain,
+            &mut mempool,
+        ) {
+            Response::Transaction(Some(transaction)) => assert_eq!(transaction, coinbase),
+            response => panic!("unexpected response: {:?}", response),
+        }
+
+        match dispatch(
+            Request::GetBalance(fixtures::address("genesis")),
+            &mut chain,
+            &mut mempool,
+        ) {
+            Response::Balance(balance) => assert_eq!(balance, Luxcoin::new(50)),
+            response => panic!("unexpected response: {:?}", response),
+        }
+    }
+
+    #[test]
+    fn submit_reports_errors() {
+        let mut chain = chain();
+        let mut mempool = Mempool::new();
+        let coinbase = chain.tip().transactions()[0].clone();
+        let spend = Transaction::new(
+            vec![TransactionInput::new(*coinbase.id(), OutputIndex::new(0))],
+            vec![
+                TransactionOutput::new(Address::new("alice".to_string()), Luxcoin::new(50))
+                    .unwrap(),
+            ],
+            0,
+        )
+        .unwrap();
+        let spend = fixtures::sign(spend, "genesis", &[&coinbase.outputs()[0]]);
+
+        let response = dispatch(
+            Request::SubmitTransaction(spend.clone()),
+            &mut chain,
+            &mut mempool,
+        );
+        assert!(matches!(response, Response::Submitted(id) if &id == spend.id()));
+        assert!(mempool.contains(spend.id()));
+
+        let response = dispatch(Request::SubmitTransaction(spend), &mut chain, &mut mempool);
+        assert!(matches!(response, Response::Error(_)));
+    }
+}
@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+
+use crate::core::{
+    block::{Block, BlockHash, BlockHeader},
+    error::LuxResult,
+};
+
+// Where a `Blockchain` keeps its blocks. Every block is stored under its id,
+// and the canonical chain is recorded as the block hash at each height.
+// Pruning drops the transactions of old canonical blocks but keeps their
+// headers.
+pub trait BlockStore {
+    fn put_block(&mut self, block: &Block) -> LuxResult<()>;
+
+    // None for unknown and pruned blocks alike
+    fn get_block(&self, hash: &BlockHash) -> LuxResult<Option<Block>>;
+
+    fn get_header(&self, hash: &BlockHash) -> LuxResult<Option<BlockHeader>>;
+
+    // Makes the stored block `hash` the canonical block at `height` and the
+    // tip, forgetting every canonical block above it
+    fn set_tip(&mut self, height: u32, hash: &BlockHash) -> LuxResult<()>;
+
+    fn get_by_height(&self, height: u32) -> LuxResult<Option<Block>>;
+
+    fn tip(&self) -> LuxResult<Option<BlockHash>>;
+
+    // Drops the transactions of every canonical block more than `keep_depth`
+    // blocks below the tip, returning the new pruned height
+    fn prune(&mut self, keep_depth: u32) -> LuxResult<u32>;
+
+    // Canonical blocks below this height only have their header stored
+    fn pruned_height(&self) -> LuxResult<u32>;
+}
+
+// Height below which `prune` drops canonical blocks, never the tip
+fn prune_height(tip_height: u32, keep_depth: u32) -> u32 {
+    tip_height.saturating_sub(keep_depth)
+}
+
+// Keeps everything in memory, so the chain is lost when the store is dropped
+#[derive(Clone, Default)]
+pub struct MemoryBlockStore {
+    blocks: HashMap<BlockHash, Block>,
+    // Headers of the blocks whose transactions were pruned
+    headers: HashMap<BlockHash, BlockHeader>,
+    heights: Vec<BlockHash>,
+    pruned_height: u32,
+}
+
+impl MemoryBlockStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BlockStore for MemoryBlockStore {
+    fn put_block(&mut self, block: &Block) -> LuxResult<()> {
+        self.blocks.insert(*block.id(), block.clone());
+        Ok(())
+    }
+
+    fn get_block(&self, hash: &BlockHash) -> LuxResult<Option<Block>> {
+        Ok(self.blocks.get(hash).cloned())
+    }
+
+    fn get_header(&self, hash: &BlockHash) -> LuxResult<Option<BlockHeader>> {
+        Ok(self
+            .blocks
+            .get(hash)
+            .map(|block| block.header())
+            .or_else(|| self.headers.get(hash))
+            .cloned())
+    }
+
+    fn set_tip(&mut self, height: u32, hash: &BlockHash) -> LuxResult<()> {
+        self.heights.truncate(height as usize);
+        self.heights.push(*hash);
+        Ok(())
+    }
+
+    fn get_by_height(&self, height: u32) -> LuxResult<Option<Block>> {
+        Ok(self
+            .heights
+            .get(height as usize)
+            .and_then(|hash| self.blocks.get(hash))
+            .cloned())
+    }
+
+    fn tip(&self) -> LuxResult<Option<BlockHash>> {
+        Ok(self.heights.last().copied())
+    }
+
+    fn prune(&mut self, keep_depth: u32) -> LuxResult<u32> {
+        let tip_height = self.heights.len().saturating_sub(1) as u32;
+        let height = prune_height(tip_height, keep_depth).max(self.pruned_height);
+        for hash in &self.heights[self.pruned_height as usize..height as usize] {
+            if let Some(block) = self.blocks.remove(hash) {
+                self.headers.insert(*hash, block.header().clone());
+            }
+        }
+        self.pruned_height = height;
+        Ok(height)
+    }
+
+    fn pruned_height(&self) -> LuxResult<u32> {
+        Ok(self.pruned_height)
+    }
+}
+
+#[cfg(feature = "sled")]
+pub mod sled_store {
+    use std::path::Path;
+
+    use super::{prune_height, BlockStore};
+    use crate::core::{
+        block::{Block, BlockHash, BlockHeader},
+        crypto::Sha256,
+        error::{LuxError, LuxResult},
+    };
+
+    // Key of the pruned height in the `meta` tree
+    const PRUNED_HEIGHT_KEY: &[u8] = b"pruned_height";
+
+    // Persists blocks in a sled database: bincode encoded blocks keyed by id,
+    // and the canonical chain keyed by big endian height so that it iterates
+    // in order. Pruned blocks leave their bincode encoded header behind.
+    pub struct SledBlockStore {
+        blocks: sled::Tree,
+        headers: sled::Tree,
+        heights: sled::Tree,
+        meta: sled::Tree,
+    }
+
+    impl SledBlockStore {
+        pub fn open<P: AsRef<Path>>(path: P) -> LuxResult<Self> {
+            Self::from_db(sled::open(path).map_err(storage_error)?)
+        }
+
+        pub fn from_db(db: sled::Db) -> LuxResult<Self> {
+            Ok(Self {
+                blocks: db.open_tree("blocks").map_err(storage_error)?,
+                headers: db.open_tree("headers").map_err(storage_error)?,
+                heights: db.open_tree("heights").map_err(storage_error)?,
+                meta: db.open_tree("meta").map_err(storage_error)?,
+            })
+        }
+
+        fn hash_at(&self, height: u32) -> LuxResult<Option<BlockHash>> {
+            self.heights
+                .get(height.to_be_bytes())
+                .map_err(storage_error)?
+                .map(|hash| to_block_hash(&hash))
+                .transpose()
+        }
+    }
+
+    impl BlockStore for SledBlockStore {
+        fn put_block(&mut self, block: &Block) -> LuxResult<()> {
+            let bytes = bincode::serialize(block).map_err(storage_error)?;
+            self.blocks
+                .insert(block.id().as_slice(), bytes)
+                .map_err(storage_error)?;
+            Ok(())
+        }
+
+        fn get_block(&self, hash: &BlockHash) -> LuxResult<Option<Block>> {
+            self.blocks
+                .get(hash.as_slice())
+                .map_err(storage_error)?
+                .map(|bytes| bincode::deserialize(&bytes).map_err(storage_error))
+                .transpose()
+        }
+
+        fn get_header(&self, hash: &BlockHash) -> LuxResult<Option<BlockHeader>> {
+            if let Some(block) = self.get_block(hash)? {
+                return Ok(Some(block.header().clone()));
+            }
+            self.headers
+                .get(hash.as_slice())
+                .map_err(storage_error)?
+                .map(|bytes| bincode::deserialize(&bytes).map_err(storage_error))
+                .transpose()
+        }
+
+        fn set_tip(&mut self, height: u32, hash: &BlockHash) -> LuxResult<()> {
+            let stale = self
+                .heights
+                .range(height.to_be_bytes()..)
+                .keys()
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(storage_error)?;
+            for key in stale {
+                self.heights.remove(key).map_err(storage_error)?;
+            }
+            self.heights
+                .insert(height.to_be_bytes(), hash.as_slice())
+                .map_err(storage_error)?;
+            self.heights.flush().map_err(storage_error)?;
+            Ok(())
+        }
+
+        fn get_by_height(&self, height: u32) -> LuxResult<Option<Block>> {
+            match self.hash_at(height)? {
+                Some(hash) => self.get_block(&hash),
+                None => Ok(None),
+            }
+        }
+
+        fn tip(&self) -> LuxResult<Option<BlockHash>> {
+            self.heights
+                .last()
+                .map_err(storage_error)?
+                .map(|(_, hash)| to_block_hash(&hash))
+                .transpose()
+        }
+
+        fn prune(&mut self, keep_depth: u32) -> LuxResult<u32> {
+            let tip_height = match self.heights.last().map_err(storage_error)? {
+                Some((height, _)) => u32::from_be_bytes(
+                    height
+                        .as_ref()
+                        .try_into()
+                        .map_err(|_| LuxError::Storage("Invalid height key.".to_string()))?,
+                ),
+                None => return self.pruned_height(),
+            };
+            let pruned_height = self.pruned_height()?;
+            let height = prune_height(tip_height, keep_depth).max(pruned_height);
+
+            for pruned in pruned_height..height {
+                let hash = match self.hash_at(pruned)? {
+                    Some(hash) => hash,
+                    None => continue,
+                };
+                if let Some(block) = self.get_block(&hash)? {
+                    let header = bincode::serialize(block.header()).map_err(storage_error)?;
+                    self.headers
+                        .insert(hash.as_slice(), header)
+                        .map_err(storage_error)?;
+                    self.blocks.remove(hash.as_slice()).map_err(storage_error)?;
+                }
+            }
+            self.meta
+                .insert(PRUNED_HEIGHT_KEY, &height.to_be_bytes())
+                .map_err(storage_error)?;
+            self.meta.flush().map_err(storage_error)?;
+            Ok(height)
+        }
+
+        fn pruned_height(&self) -> LuxResult<u32> {
+            match self.meta.get(PRUNED_HEIGHT_KEY).map_err(storage_error)? {
+                Some(bytes) => bytes
+                    .as_ref()
+                    .try_into()
+                    .map(u32::from_be_bytes)
+                    .map_err(|_| LuxError::Storage("Invalid pruned height.".to_string())),
+                None => Ok(0),
+            }
+        }
+    }
+
+    fn to_block_hash(bytes: &[u8]) -> LuxResult<BlockHash> {
+        Sha256::try_from(bytes).map(BlockHash::new)
+    }
+
+    fn storage_error<E: std::error::Error>(error: E) -> LuxError {
+        LuxError::Storage(error.to_string())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::SledBlockStore;
+        use crate::core::{
+            address::Address, block::Block, consensus::ConsensusParams, fixtures, luxcoin::Luxcoin,
+            store::BlockStore,
+        };
+
+        #[test]
+        fn round_trips_blocks() {
+            let db = sled::Config::new().temporary(true).open().unwrap();
+            let mut store = SledBlockStore::from_db(db).unwrap();
+            let genesis = Block::genesis(
+                Address::new("genesis".to_string()),
+                Luxcoin::new(50),
+                0,
+                &ConsensusParams::mainnet(),
+            )
+            .unwrap();
+
+            store.put_block(&genesis).unwrap();
+            store.set_tip(0, genesis.id()).unwrap();
+
+            assert_eq!(store.tip().unwrap(), Some(*genesis.id()));
+            let stored = store.get_by_height(0).unwrap().unwrap();
+            assert_eq!(stored.id(), genesis.id());
+            assert_eq!(stored.transactions(), genesis.transactions());
+        }
+
+        #[test]
+        fn prune_keeps_headers() {
+            let db = sled::Config::new().temporary(true).open().unwrap();
+            let mut store = SledBlockStore::from_db(db).unwrap();
+            let genesis = Block::genesis(
+                Address::new("genesis".to_string()),
+                Luxcoin::new(50),
+                0,
+                &ConsensusParams::mainnet(),
+            )
+            .unwrap();
+            let next = fixtures::random_block(genesis.id());
+            for (height, block) in [&genesis, &next].iter().enumerate() {
+                store.put_block(block).unwrap();
+                store.set_tip(height as u32, block.id()).unwrap();
+            }
+
+            assert_eq!(store.prune(0).unwrap(), 1);
+            assert_eq!(store.pruned_height().unwrap(), 1);
+            assert!(store.get_block(genesis.id()).unwrap().is_none());
+            assert_eq!(
+                store.get_header(genesis.id()).unwrap().unwrap().hash(),
+                *genesis.id()
+            );
+            assert!(store.get_block(next.id()).unwrap().is_some());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockStore, MemoryBlockStore};
+    use crate::core::{
+        address::Address,
+        block::{Block, BlockHash, BlockHeader},
+        crypto::MerkleTree,
+        luxcoin::Luxcoin,
+        timestamp::Timestamp,
+        transaction::{Transaction, TransactionInput, TransactionOutput},
+    };
+
+    fn block(previous: &BlockHash, height: u32, to: &str) -> Block {
+        let coinbase = Transaction::new(
+            vec![TransactionInput::new_coinbase(height)],
+            vec![TransactionOutput::new(Address::new(to.to_string()), Luxcoin::new(50)).unwrap()],
+            0,
+        )
+        .unwrap();
+        let transactions = vec![coinbase];
+        let merkle_root = MerkleTree::from(&transactions).root().clone();
+        let header = BlockHeader::new(*previous, merkle_root, Timestamp::new(0), 0, 0);
+        Block::new(header, transactions)
+    }
+
+    #[test]
+    fn set_tip_forgets_higher_blocks() {
+        let mut store = MemoryBlockStore::new();
+        let genesis = block(&BlockHash::zero(), 0, "genesis");
+        let first = block(genesis.id(), 1, "miner");
+        let second = block(first.id(), 2, "miner");
+        let fork = block(genesis.id(), 1, "fork");
+        for (height, block) in [&genesis, &first, &second].iter().enumerate() {
+            store.put_block(block).unwrap();
+            store.set_tip(height as u32, block.id()).unwrap();
+        }
+
+        store.put_block(&fork).unwrap();
+        store.set_tip(1, fork.id()).unwrap();
+
+        assert_eq!(store.tip().unwrap(), Some(*fork.id()));
+        assert!(store.get_by_height(2).unwrap().is_none());
+        assert!(store.get_block(second.id()).unwrap().is_some());
+    }
+
+    #[test]
+    fn prune_drops_old_bodies_but_keeps_headers() {
+        let mut store = MemoryBlockStore::new();
+        let genesis = block(&BlockHash::zero(), 0, "genesis");
+        let first = block(genesis.id(), 1, "miner");
+        let second = block(first.id(), 2, "miner");
+        for (height, block) in [&genesis, &first, &second].iter().enumerate() {
+            store.put_block(block).unwrap();
+            store.set_tip(height as u32, block.id()).unwrap();
+        }
+
+        assert_eq!(store.prune(1).unwrap(), 1);
+        assert!(store.get_block(genesis.id()).unwrap().is_none());
+        assert_eq!(
+            store
+                .get_header(genesis.id())
+                .unwrap()
+                .map(|header| header.hash()),
+            Some(*genesis.id())
+        );
+        assert!(store.get_block(first.id()).unwrap().is_some());
+
+        // Pruning never goes back down, and never drops the tip
+        assert_eq!(store.prune(5).unwrap(), 1);
+        assert_eq!(store.prune(0).unwrap(), 2);
+        assert_eq!(store.pruned_height().unwrap(), 2);
+        assert!(store.get_block(second.id()).unwrap().is_some());
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    fmt::{self, Display},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::error::{LuxError, LuxResult};
+
+// Number of previous blocks whose median timestamp a new block must exceed
+pub const MEDIAN_TIME_SPAN: usize = 11;
+
+// Seconds since the Unix epoch. Serialized as a bare u32.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Timestamp(u32);
+
+impl Timestamp {
+    pub const fn new(secs: u32) -> Self {
+        Self(secs)
+    }
+
+    // Saturates at the bounds of u32 should the clock be out of range
+    pub fn now() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        Self(secs.min(u32::MAX as u64) as u32)
+    }
+
+    pub fn as_secs(&self) -> u32 {
+        self.0
+    }
+
+    pub fn checked_add_secs(self, secs: u32) -> Option<Self> {
+        self.0.checked_add(secs).map(Self)
+    }
+
+    // A new block's timestamp must be strictly greater than the median of the
+    // last `MEDIAN_TIME_SPAN` blocks before it
+    pub fn validate_after(&self, previous: &[Timestamp]) -> LuxResult<()> {
+        match median_time_past(previous) {
+            Some(median) if *self <= median => Err(LuxError::InvalidTimestamp(format!(
+                "{} is not after the median time past {}",
+                self, median
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Median of the last `MEDIAN_TIME_SPAN` timestamps, or None if there are none
+pub fn median_time_past(timestamps: &[Timestamp]) -> Option<Timestamp> {
+    median(&timestamps[timestamps.len().saturating_sub(MEDIAN_TIME_SPAN)..])
+}
+
+// Upper median, in any order, or None if there are no timestamps
+pub fn median(timestamps: &[Timestamp]) -> Option<Timestamp> {
+    let mut sorted = timestamps.to_vec();
+    sorted.sort();
+    sorted.get(sorted.len() / 2).copied()
+}
+
+// ISO-8601 in UTC, e.g. 2021-11-06T00:00:00Z
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.0 / 86_400;
+        let secs_of_day = self.0 % 86_400;
+
+        // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let day_of_era = z - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + (month <= 2) as u32;
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            secs_of_day / 3_600,
+            secs_of_day % 3_600 / 60,
+            secs_of_day % 60
+        )
+    }
+}
+
+impl From<u32> for Timestamp {
+    fn from(secs: u32) -> Self {
+        Self::new(secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{median_time_past, Timestamp};
+
+    #[test]
+    fn display_iso_8601() {
+        assert_eq!(Timestamp::new(0).to_string(), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            Timestamp::new(951_782_400).to_string(),
+            "2000-02-29T00:00:00Z"
+        );
+        assert_eq!(
+            Timestamp::new(1_636_156_800).to_string(),
+            "2021-11-06T00:00:00Z"
+        );
+        assert_eq!(Timestamp::new(u32::MAX).to_string(), "2106-02-07T06:28:15Z");
+    }
+
+    #[test]
+    fn serializes_as_u32() {
+        let json = serde_json::to_string(&Timestamp::new(1_636_156_800)).unwrap();
+        assert_eq!(json, "1636156800");
+        assert_eq!(
+            serde_json::from_str::<Timestamp>(&json).unwrap(),
+            Timestamp::new(1_636_156_800)
+        );
+    }
+
+    #[test]
+    fn median_of_the_last_eleven() {
+        let timestamps: Vec<Timestamp> = [100, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]
+            .iter()
+            .map(|secs| Timestamp::new(*secs))
+            .collect();
+
+        assert_eq!(median_time_past(&timestamps), Some(Timestamp::new(6)));
+        assert_eq!(median_time_past(&timestamps[..3]), Some(Timestamp::new(2)));
+        assert_eq!(median_time_past(&[]), None);
+    }
+
+    #[test]
+    fn validate_after_median() {
+        let previous: Vec<Timestamp> = [10, 20, 30].iter().map(|s| Timestamp::new(*s)).collect();
+
+        assert!(Timestamp::new(21).validate_after(&previous).is_ok());
+        assert!(Timestamp::new(20).validate_after(&previous).is_err());
+        assert!(Timestamp::new(0).validate_after(&[]).is_ok());
+    }
+}
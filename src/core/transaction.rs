@@ -1,4 +1,9 @@
-use std::fmt::{self, Display};
+use std::{
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+    str::FromStr,
+    sync::OnceLock,
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -6,7 +11,10 @@ use crate::core::{
     address::Address,
     crypto::{self, Sha256},
     error::LuxError,
+    keys::{KeyPair, PublicKey, Signature},
     luxcoin::Luxcoin,
+    timestamp::Timestamp,
+    utxo::UtxoSet,
 };
 
 use super::error::LuxResult;
@@ -26,19 +34,37 @@ impl AsRef<Sha256> for TransactionId {
     }
 }
 
+impl FromStr for TransactionId {
+    type Err = LuxError;
+
+    fn from_str(s: &str) -> LuxResult<Self> {
+        s.parse().map(Self)
+    }
+}
+
 impl Display for TransactionId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.0)
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OutputIndex(i32);
 
 impl OutputIndex {
     pub const fn new(index: i32) -> Self {
         Self(index)
     }
+
+    // Either a position in an output list or the coinbase sentinel
+    pub fn is_valid(&self) -> bool {
+        self.0 >= 0 || *self == COINBASE_OUTPUT_INDEX
+    }
+
+    // The position in an output list, None for negative indices
+    pub fn as_usize(&self) -> Option<usize> {
+        usize::try_from(self.0).ok()
+    }
 }
 
 impl Display for OutputIndex {
@@ -52,10 +78,21 @@ const COINBASE_UTXO_ID: TransactionId = TransactionId(Sha256::new([0; 32]));
 // Set all bits to 1
 const COINBASE_OUTPUT_INDEX: OutputIndex = OutputIndex::new(-1);
 
+// Serialized size of an input carrying a signature and public key, which is
+// what spending an output adds to a transaction
+pub const SIGNED_INPUT_SIZE: usize = 249;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TransactionInput {
     utxo_id: TransactionId,
     output_index: OutputIndex,
+    // Proof of ownership of the referenced output. Not part of the
+    // transaction id, so signing doesn't change it.
+    signature: Option<Signature>,
+    pubkey: Option<PublicKey>,
+    // Height of the block a coinbase input belongs to, so that otherwise
+    // identical coinbases at different heights get different ids
+    coinbase_height: Option<u32>,
 }
 
 impl TransactionInput {
@@ -63,6 +100,9 @@ impl TransactionInput {
         Self {
             utxo_id,
             output_index,
+            signature: None,
+            pubkey: None,
+            coinbase_height: None,
         }
     }
 
@@ -74,56 +114,210 @@ impl TransactionInput {
         &self.output_index
     }
 
-    pub fn new_coinbase() -> Self {
+    pub fn signature(&self) -> Option<&Signature> {
+        self.signature.as_ref()
+    }
+
+    pub fn pubkey(&self) -> Option<&PublicKey> {
+        self.pubkey.as_ref()
+    }
+
+    pub fn new_coinbase(height: u32) -> Self {
         Self {
-            utxo_id: COINBASE_UTXO_ID,
-            output_index: COINBASE_OUTPUT_INDEX,
+            coinbase_height: Some(height),
+            ..Self::new(COINBASE_UTXO_ID, COINBASE_OUTPUT_INDEX)
         }
     }
 
+    pub fn coinbase_height(&self) -> Option<u32> {
+        self.coinbase_height
+    }
+
     pub fn is_coinbase(&self) -> bool {
         self.utxo_id == COINBASE_UTXO_ID && self.output_index == COINBASE_OUTPUT_INDEX
     }
 }
 
+impl TransactionInput {
+    // Id encoding: outpoint, then a 0 byte or a 1 byte followed by the coinbase
+    // height
+    fn encode(&self, hasher: &mut crypto::Sha256Hasher) {
+        hasher.update(self.utxo_id.as_ref().as_slice());
+        hasher.update(&self.output_index.0.to_le_bytes());
+        match self.coinbase_height {
+            Some(height) => {
+                hasher.update(&[1]);
+                hasher.update(&height.to_le_bytes());
+            }
+            None => hasher.update(&[0]),
+        }
+    }
+}
+
 impl Display for TransactionInput {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}{}", self.utxo_id, self.output_index)
+        write!(f, "{}{}", self.utxo_id, self.output_index)?;
+        if let Some(height) = self.coinbase_height {
+            write!(f, "{}", height)?;
+        }
+        Ok(())
     }
 }
 
+// Largest payload a data output may carry
+pub const MAX_DATA_OUTPUT_SIZE: usize = 80;
+
+// Stands in for the address length in the id encoding of data outputs. No
+// address is that long, so data and payment outputs never share an encoding.
+const DATA_OUTPUT_MARKER: u32 = u32::MAX;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputKind {
+    Payment { to: Address, amount: Luxcoin },
+    // Arbitrary bytes, such as a commitment to a document, carrying no value.
+    // Nothing can spend it, so it never enters the UTXO set.
+    Data(#[serde(with = "serde_bytes")] Vec<u8>),
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct TransactionOutput {
-    to: Address,
-    amount: Luxcoin,
+    kind: OutputKind,
 }
 
-impl<'a> TransactionOutput {
-    pub fn new(to: Address, amount: Luxcoin) -> Self {
-        Self { to, amount }
+impl TransactionOutput {
+    pub fn new(to: Address, amount: Luxcoin) -> LuxResult<Self> {
+        Self::from_kind(OutputKind::Payment { to, amount })
     }
 
-    pub fn to(&self) -> &Address {
-        &self.to
+    // An unspendable output embedding `data`, at most `MAX_DATA_OUTPUT_SIZE`
+    // bytes
+    pub fn new_data(data: Vec<u8>) -> LuxResult<Self> {
+        Self::from_kind(OutputKind::Data(data))
     }
 
+    fn from_kind(kind: OutputKind) -> LuxResult<Self> {
+        let output = Self { kind };
+        output.validate()?;
+        Ok(output)
+    }
+
+    pub fn kind(&self) -> &OutputKind {
+        &self.kind
+    }
+
+    // The address paid, None for data outputs
+    pub fn to(&self) -> Option<&Address> {
+        match &self.kind {
+            OutputKind::Payment { to, .. } => Some(to),
+            OutputKind::Data(_) => None,
+        }
+    }
+
+    // Zero for data outputs
     pub fn amount(&self) -> Luxcoin {
-        self.amount
+        match &self.kind {
+            OutputKind::Payment { amount, .. } => *amount,
+            OutputKind::Data(_) => Luxcoin::ZERO,
+        }
+    }
+
+    pub fn data(&self) -> Option<&[u8]> {
+        match &self.kind {
+            OutputKind::Payment { .. } => None,
+            OutputKind::Data(data) => Some(data),
+        }
+    }
+
+    // Whether the output can be spent, and so belongs in the UTXO set
+    pub fn is_spendable(&self) -> bool {
+        matches!(self.kind, OutputKind::Payment { .. })
+    }
+
+    // Payments must carry a positive amount and data must fit in
+    // `MAX_DATA_OUTPUT_SIZE`
+    fn validate(&self) -> LuxResult<()> {
+        match &self.kind {
+            OutputKind::Payment { amount, .. } if !amount.is_positive() => {
+                Err(LuxError::InvalidAmount(*amount))
+            }
+            OutputKind::Data(data) if data.len() > MAX_DATA_OUTPUT_SIZE => {
+                Err(LuxError::InvalidTransaction(format!(
+                    "Data output of {} bytes exceeds the limit of: {} bytes.",
+                    data.len(),
+                    MAX_DATA_OUTPUT_SIZE
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl TransactionOutput {
+    // Id encoding: length prefixed address, then the amount. Data outputs
+    // write `DATA_OUTPUT_MARKER` in place of the address length, followed by
+    // the length prefixed data.
+    fn encode(&self, hasher: &mut crypto::Sha256Hasher) {
+        match &self.kind {
+            OutputKind::Payment { to, amount } => {
+                let address = to.as_str().as_bytes();
+                hasher.update(&(address.len() as u32).to_le_bytes());
+                hasher.update(address);
+                hasher.update(&amount.as_i64().to_le_bytes());
+            }
+            OutputKind::Data(data) => {
+                hasher.update(&DATA_OUTPUT_MARKER.to_le_bytes());
+                hasher.update(&(data.len() as u32).to_le_bytes());
+                hasher.update(data);
+            }
+        }
     }
 }
 
 impl Display for TransactionOutput {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}{}", self.to, self.amount)
+        match &self.kind {
+            OutputKind::Payment { to, amount } => write!(f, "{}{}", to, amount),
+            OutputKind::Data(data) => write!(f, "data:{}", crypto::as_hex(data)),
+        }
     }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "TransactionData")]
 pub struct Transaction {
     id: TransactionId,
     inputs: Vec<TransactionInput>,
     outputs: Vec<TransactionOutput>,
-    // Minimum block height this transaction can be included in
-    // to avoid collisions with where transactions have the same io
+    // Earliest block this transaction can be included in, see
+    // `Transaction::is_final`. Part of the id, so it can't be altered after
+    // signing.
     locktime: u32,
+    version: u32,
+    // Computed on first use, see `Transaction::weight`
+    #[serde(skip)]
+    weight: OnceLock<u64>,
+}
+
+// Weight units per serialized byte. Every byte counts fully for now, the
+// factor leaves room to discount signature data later.
+pub const WITNESS_SCALE_FACTOR: u64 = 4;
+
+// Locktimes below this are block heights, the rest are unix timestamps
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+// Which parts of a transaction an input's signature covers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SighashType {
+    // Every input and output
+    All = 1,
+}
+
+// Transaction version produced by this node, and the only one it accepts
+pub const TRANSACTION_VERSION: u32 = 1;
+
+fn default_transaction_version() -> u32 {
+    TRANSACTION_VERSION
 }
 
 impl Transaction {
@@ -132,21 +326,74 @@ impl Transaction {
         outputs: Vec<TransactionOutput>,
         locktime: u32,
     ) -> LuxResult<Self> {
-        let id = Self::hash_transaction_data(&inputs, &outputs);
+        Self::with_version(TRANSACTION_VERSION, inputs, outputs, locktime)
+    }
+
+    pub fn with_version(
+        version: u32,
+        inputs: Vec<TransactionInput>,
+        outputs: Vec<TransactionOutput>,
+        locktime: u32,
+    ) -> LuxResult<Self> {
+        let id = Self::hash_transaction_data(version, &inputs, &outputs, locktime);
         let transaction = Self {
             id,
             inputs,
             outputs,
             locktime,
+            version,
+            weight: OnceLock::new(),
         };
         transaction.validate_format()?;
         Ok(transaction)
     }
 
+    // Builds the single input, single output coinbase of the block at
+    // `height`, paying the subsidy plus the fees collected by the block
+    pub fn new_coinbase(
+        to: Address,
+        subsidy: Luxcoin,
+        fees: Luxcoin,
+        height: u32,
+    ) -> LuxResult<Self> {
+        let amount = subsidy.checked_add(fees).ok_or_else(|| {
+            LuxError::InvalidTransaction(format!(
+                "Coinbase subsidy: {} plus fees: {} overflows.",
+                subsidy, fees
+            ))
+        })?;
+
+        Self::new(
+            vec![TransactionInput::new_coinbase(height)],
+            vec![TransactionOutput::new(to, amount)?],
+            0,
+        )
+    }
+
     pub fn id(&self) -> &TransactionId {
         &self.id
     }
 
+    pub fn locktime(&self) -> u32 {
+        self.locktime
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    // Whether the transaction may be included in a block at `block_height`
+    // mined at `block_time`. Like Bitcoin, a locktime below
+    // `LOCKTIME_THRESHOLD` is a block height and anything above is a unix
+    // timestamp. A zero locktime is always final.
+    pub fn is_final(&self, block_height: u32, block_time: Timestamp) -> bool {
+        if self.locktime < LOCKTIME_THRESHOLD {
+            block_height >= self.locktime
+        } else {
+            block_time.as_secs() >= self.locktime
+        }
+    }
+
     pub fn inputs(&self) -> &Vec<TransactionInput> {
         &self.inputs
     }
@@ -155,12 +402,280 @@ impl Transaction {
         &self.outputs
     }
 
+    // Length of the binary (bincode) encoding, computed without serializing
+    // into a buffer
+    pub fn serialized_size(&self) -> usize {
+        // Every field has an infallible Serialize impl
+        bincode::serialized_size(self).expect("transactions always serialize") as usize
+    }
+
+    // Size measure block limits and fee rates use, currently the serialized
+    // size times `WITNESS_SCALE_FACTOR`
+    pub fn weight(&self) -> u64 {
+        *self
+            .weight
+            .get_or_init(|| self.serialized_size() as u64 * WITNESS_SCALE_FACTOR)
+    }
+
+    // Checks that the id matches the transaction contents
+    pub fn verify_id(&self) -> bool {
+        self.id
+            == Self::hash_transaction_data(self.version, &self.inputs, &self.outputs, self.locktime)
+    }
+
+    // Signs the input at `index`, which spends `prev_output`, storing the
+    // signature and the public key alongside it
+    pub fn sign_input(
+        &mut self,
+        index: usize,
+        keypair: &KeyPair,
+        prev_output: &TransactionOutput,
+    ) -> LuxResult<()> {
+        let msg = self.sighash(index, prev_output)?;
+        let input = &mut self.inputs[index];
+        if input.is_coinbase() {
+            return Err(LuxError::InvalidTransaction(format!(
+                "Transaction: {} can't sign its coinbase input.",
+                self.id
+            )));
+        }
+
+        input.signature = Some(keypair.sign(&msg));
+        input.pubkey = Some(keypair.public_key());
+        // Signing grows the transaction
+        self.weight = OnceLock::new();
+        Ok(())
+    }
+
+    // Checks that the input at `index` carries a valid signature for this
+    // transaction spending `prev_output`. It doesn't check that the key owns
+    // the referenced output, see `verify_inputs_with` for that.
+    pub fn verify_input(&self, index: usize, prev_output: &TransactionOutput) -> bool {
+        let msg = match self.sighash(index, prev_output) {
+            Ok(msg) => msg,
+            Err(_) => return false,
+        };
+
+        match &self.inputs[index] {
+            TransactionInput {
+                signature: Some(signature),
+                pubkey: Some(pubkey),
+                ..
+            } => signature.verify(&msg, pubkey),
+            _ => false,
+        }
+    }
+
+    // Checks that every input carries a valid signature by the key the output
+    // it spends pays to, with outputs resolved by `lookup`. Coinbase inputs
+    // spend nothing and need no signature.
+    pub fn verify_inputs_with<'a, F>(&self, lookup: F) -> LuxResult<()>
+    where
+        F: Fn(&TransactionId, &OutputIndex) -> Option<&'a TransactionOutput>,
+    {
+        for (i, input) in self.inputs.iter().enumerate() {
+            if input.is_coinbase() {
+                continue;
+            }
+            let (utxo, index) = (*input.utxo_id(), *input.output_index());
+            let prev_output =
+                lookup(&utxo, &index).ok_or(LuxError::MissingOutput { utxo, index })?;
+
+            let owner = prev_output.to().and_then(Address::pubkey_hash);
+            let signer = input.pubkey.as_ref().map(PublicKey::hash160);
+            if owner.is_none() || owner != signer || !self.verify_input(i, prev_output) {
+                return Err(LuxError::InvalidSignature { utxo, index });
+            }
+        }
+
+        Ok(())
+    }
+
+    // The message signed for the input at `input_index`, see `sighash_with`
+    pub fn sighash(
+        &self,
+        input_index: usize,
+        prev_output: &TransactionOutput,
+    ) -> LuxResult<Sha256> {
+        self.sighash_with(input_index, prev_output, SighashType::All)
+    }
+
+    // Double SHA-256 of the sighash type, the transaction id, the input's
+    // position and the output it spends. The id already commits to every
+    // input, output and the locktime, and committing to the spent output
+    // binds the signature to the amount being spent.
+    pub fn sighash_with(
+        &self,
+        input_index: usize,
+        prev_output: &TransactionOutput,
+        sighash_type: SighashType,
+    ) -> LuxResult<Sha256> {
+        if input_index >= self.inputs.len() {
+            return Err(LuxError::InvalidTransaction(format!(
+                "Transaction: {} has no input {}.",
+                self.id, input_index
+            )));
+        }
+
+        let mut hasher = crypto::Sha256Hasher::new();
+        hasher.update(&(sighash_type as u32).to_le_bytes());
+        hasher.update(self.id.as_ref().as_slice());
+        hasher.update(&(input_index as u32).to_le_bytes());
+        prev_output.encode(&mut hasher);
+        Ok(hasher.finalize256())
+    }
+
+    // Sum of every output. Transactions whose outputs overflow are rejected on
+    // construction, so the total is always exact.
+    pub fn total_output(&self) -> Luxcoin {
+        Luxcoin::checked_sum(self.outputs.iter().map(TransactionOutput::amount))
+            .expect("output totals are checked on construction")
+    }
+
+    // Sum of the outputs spent by the inputs, looked up in `utxos`. Coinbase
+    // inputs spend nothing.
+    pub fn total_input(&self, utxos: &UtxoSet) -> LuxResult<Luxcoin> {
+        self.total_input_with(|utxo_id, index| {
+            utxos.get(utxo_id, index).map(TransactionOutput::amount)
+        })
+    }
+
+    pub fn total_input_with<F>(&self, lookup: F) -> LuxResult<Luxcoin>
+    where
+        F: Fn(&TransactionId, &OutputIndex) -> Option<Luxcoin>,
+    {
+        if self.is_coinbase() {
+            return Ok(Luxcoin::ZERO);
+        }
+
+        let input_amounts = self
+            .inputs
+            .iter()
+            .map(|input| {
+                lookup(input.utxo_id(), input.output_index()).ok_or(LuxError::MissingOutput {
+                    utxo: input.utxo_id,
+                    index: input.output_index,
+                })
+            })
+            .collect::<LuxResult<Vec<Luxcoin>>>()?;
+
+        self.checked_total(input_amounts)
+    }
+
+    // The amount by which the inputs exceed the outputs, left to the miner
+    pub fn fee(&self, utxos: &UtxoSet) -> LuxResult<Luxcoin> {
+        self.validate_amounts(utxos)
+    }
+
+    // Looks up the value of every input and returns the fee, the amount by
+    // which the inputs exceed the outputs. Coinbase transactions have no
+    // inputs to look up, see `Transaction::validate_coinbase_amount`.
+    pub fn validate_amounts(&self, utxos: &UtxoSet) -> LuxResult<Luxcoin> {
+        self.validate_amounts_with(|utxo_id, index| {
+            utxos.get(utxo_id, index).map(TransactionOutput::amount)
+        })
+    }
+
+    // Same as `validate_amounts`, with input amounts resolved by `lookup`, for
+    // callers that know of outputs the UTXO set doesn't hold yet
+    pub fn validate_amounts_with<F>(&self, lookup: F) -> LuxResult<Luxcoin>
+    where
+        F: Fn(&TransactionId, &OutputIndex) -> Option<Luxcoin>,
+    {
+        if self.is_coinbase() {
+            return Ok(Luxcoin::ZERO);
+        }
+
+        let total_input = self.total_input_with(lookup)?;
+        let total_output = self.total_output();
+        if total_output > total_input {
+            return Err(LuxError::InsufficientFunds {
+                required: total_output,
+                available: total_input,
+            });
+        }
+
+        Ok(total_input - total_output)
+    }
+
+    // A coinbase may claim at most the block subsidy plus the collected fees
+    pub fn validate_coinbase_amount(&self, allowed: Luxcoin) -> LuxResult<()> {
+        let total_output = self.total_output();
+        if total_output > allowed {
+            return Err(LuxError::InvalidTransaction(format!(
+                "Coinbase transaction: {} claims {} but only {} is allowed.",
+                self.id, total_output, allowed
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn checked_total<I: IntoIterator<Item = Luxcoin>>(&self, amounts: I) -> LuxResult<Luxcoin> {
+        Luxcoin::checked_sum(amounts).ok_or_else(|| {
+            LuxError::InvalidTransaction(format!("Transaction: {} amounts overflow.", self.id))
+        })
+    }
+
     pub fn is_coinbase(&self) -> bool {
-        self.inputs.get(0).unwrap().is_coinbase()
+        // `validate_format` rejects transactions without inputs, `first` just
+        // keeps this from relying on it
+        self.inputs
+            .first()
+            .is_some_and(TransactionInput::is_coinbase)
     }
 
     // Ensures transaction is valid under Coinbase standards
     fn validate_format(&self) -> LuxResult<()> {
+        if self.version != TRANSACTION_VERSION {
+            return Err(LuxError::InvalidTransaction(format!(
+                "Transaction: {} has unsupported version: {}.",
+                self.id, self.version
+            )));
+        }
+
+        // A coinbase has exactly one input, so every transaction spends something
+        if self.inputs.is_empty() {
+            return Err(LuxError::InvalidTransaction(format!(
+                "Transaction: {} has no inputs.",
+                self.id
+            )));
+        }
+        if self.outputs.is_empty() {
+            return Err(LuxError::InvalidTransaction(format!(
+                "Transaction: {} has no outputs.",
+                self.id
+            )));
+        }
+
+        // Outputs are checked on construction, but deserialized ones are not
+        self.outputs
+            .iter()
+            .try_for_each(TransactionOutput::validate)?;
+        self.checked_total(self.outputs.iter().map(TransactionOutput::amount))?;
+
+        if let Some(input) = self
+            .inputs
+            .iter()
+            .find(|input| !input.output_index.is_valid())
+        {
+            return Err(LuxError::InvalidTransaction(format!(
+                "Transaction: {} has an input with the invalid output index: {}.",
+                self.id, input.output_index
+            )));
+        }
+
+        if let Some(input) = self
+            .inputs
+            .iter()
+            .find(|input| !input.is_coinbase() && input.coinbase_height.is_some())
+        {
+            return Err(LuxError::InvalidTransaction(format!(
+                "Transaction: {} sets a coinbase height on the non coinbase input: {}.",
+                self.id, input
+            )));
+        }
+
         let contains_coinbase_inputs = self.inputs.iter().any(TransactionInput::is_coinbase);
         let coinbase_requirements_satisfied = self.inputs.len() == 1 && self.outputs.len() == 1;
         if contains_coinbase_inputs && !coinbase_requirements_satisfied {
@@ -170,23 +685,633 @@ impl Transaction {
         }
     }
 
+    // Transaction ids are the double SHA-256 (`crypto::hash256`) of the
+    // canonical encoding of the version, the inputs, the outputs and the
+    // locktime. Lists and addresses are prefixed with their length and
+    // integers are little endian, so no two distinct transactions share an
+    // encoding. Signatures are left out.
     fn hash_transaction_data(
-        inputs: &Vec<TransactionInput>,
-        outputs: &Vec<TransactionOutput>,
+        version: u32,
+        inputs: &[TransactionInput],
+        outputs: &[TransactionOutput],
+        locktime: u32,
     ) -> TransactionId {
-        let data = format!(
-            "{}{}",
-            inputs
-                .iter()
-                .map(TransactionInput::to_string)
-                .collect::<Vec<String>>()
-                .join(""),
-            outputs
-                .iter()
-                .map(TransactionOutput::to_string)
-                .collect::<Vec<String>>()
-                .join("")
+        let mut hasher = crypto::Sha256Hasher::new();
+        hasher.update(&version.to_le_bytes());
+        hasher.update(&(inputs.len() as u32).to_le_bytes());
+        inputs.iter().for_each(|input| input.encode(&mut hasher));
+        hasher.update(&(outputs.len() as u32).to_le_bytes());
+        outputs.iter().for_each(|output| output.encode(&mut hasher));
+        hasher.update(&locktime.to_le_bytes());
+
+        TransactionId(hasher.finalize256())
+    }
+}
+
+// Assembles a transaction one input and output at a time. Outputs are checked
+// as they are added, and `build` runs the same checks as `Transaction::new`.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionBuilder {
+    inputs: Vec<TransactionInput>,
+    outputs: Vec<TransactionOutput>,
+    locktime: u32,
+}
+
+impl TransactionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Spends output `index` of the transaction `utxo_id`
+    pub fn add_input(mut self, utxo_id: TransactionId, index: OutputIndex) -> Self {
+        self.inputs.push(TransactionInput::new(utxo_id, index));
+        self
+    }
+
+    pub fn add_output(mut self, to: Address, amount: Luxcoin) -> LuxResult<Self> {
+        self.outputs.push(TransactionOutput::new(to, amount)?);
+        Ok(self)
+    }
+
+    // Embeds `data` in an unspendable output, see `TransactionOutput::new_data`
+    pub fn add_data_output(mut self, data: Vec<u8>) -> LuxResult<Self> {
+        self.outputs.push(TransactionOutput::new_data(data)?);
+        Ok(self)
+    }
+
+    pub fn locktime(mut self, locktime: u32) -> Self {
+        self.locktime = locktime;
+        self
+    }
+
+    pub fn build(self) -> LuxResult<Transaction> {
+        Transaction::new(self.inputs, self.outputs, self.locktime)
+    }
+}
+
+// Transactions are equal when their ids are. The id commits to the inputs and
+// outputs but not to signatures, so two copies of a transaction signed
+// differently compare equal, as they spend and create the same outputs.
+impl PartialEq for Transaction {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Transaction {}
+
+impl Hash for Transaction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state)
+    }
+}
+
+// Wire form of a `Transaction`. The serialized id is not trusted, it must match
+// the one recomputed from the inputs and outputs.
+#[derive(Deserialize)]
+struct TransactionData {
+    id: TransactionId,
+    inputs: Vec<TransactionInput>,
+    outputs: Vec<TransactionOutput>,
+    locktime: u32,
+    // Serialized last and defaulted so transactions written before versioning
+    // still deserialize
+    #[serde(default = "default_transaction_version")]
+    version: u32,
+}
+
+impl TryFrom<TransactionData> for Transaction {
+    type Error = LuxError;
+
+    fn try_from(data: TransactionData) -> LuxResult<Self> {
+        let transaction =
+            Self::with_version(data.version, data.inputs, data.outputs, data.locktime)?;
+        if transaction.id != data.id {
+            return Err(LuxError::TransactionIdMismatch {
+                expected: transaction.id,
+                actual: data.id,
+            });
+        }
+
+        Ok(transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{
+        OutputIndex, SighashType, Transaction, TransactionBuilder, TransactionId, TransactionInput,
+        TransactionOutput, MAX_DATA_OUTPUT_SIZE, SIGNED_INPUT_SIZE, TRANSACTION_VERSION,
+        WITNESS_SCALE_FACTOR,
+    };
+    use crate::core::{
+        address::Address,
+        block::Block,
+        consensus::{ConsensusParams, Network},
+        crypto::{self, Sha256},
+        error::LuxError,
+        keys::KeyPair,
+        luxcoin::Luxcoin,
+        timestamp::Timestamp,
+        utxo::UtxoSet,
+    };
+
+    fn spent_output(amount: i64) -> TransactionOutput {
+        TransactionOutput::new(Address::new("carol".to_string()), Luxcoin::new(amount)).unwrap()
+    }
+
+    fn transaction() -> Transaction {
+        Transaction::new(
+            vec![TransactionInput::new(
+                TransactionId::new(crypto::hash(b"previous")),
+                OutputIndex::new(0),
+            )],
+            vec![
+                TransactionOutput::new(Address::new("alice".to_string()), Luxcoin::new(30))
+                    .unwrap(),
+                TransactionOutput::new(Address::new("bob".to_string()), Luxcoin::new(12)).unwrap(),
+            ],
+            0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn totals_and_fee() {
+        let transaction = transaction();
+        let genesis = Block::genesis(
+            Address::new("carol".to_string()),
+            Luxcoin::new(50),
+            0,
+            &ConsensusParams::mainnet(),
+        )
+        .unwrap();
+        let funding = &genesis.transactions()[0];
+        let mut utxos = UtxoSet::new();
+        utxos.apply_block(&genesis).unwrap();
+        let spend = Transaction::new(
+            vec![TransactionInput::new(*funding.id(), OutputIndex::new(0))],
+            transaction.outputs().clone(),
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(spend.total_output(), Luxcoin::new(42));
+        assert_eq!(spend.total_input(&utxos).unwrap(), Luxcoin::new(50));
+        assert_eq!(spend.fee(&utxos).unwrap(), Luxcoin::new(8));
+        assert!(matches!(
+            transaction.total_input(&utxos),
+            Err(LuxError::MissingOutput { .. })
+        ));
+    }
+
+    #[test]
+    fn overflowing_outputs_are_rejected() {
+        let output =
+            TransactionOutput::new(Address::new("alice".to_string()), Luxcoin::new(i64::MAX))
+                .unwrap();
+        let result = Transaction::new(
+            vec![TransactionInput::new(
+                TransactionId::new(crypto::hash(b"previous")),
+                OutputIndex::new(0),
+            )],
+            vec![output.clone(), output],
+            0,
+        );
+
+        assert!(matches!(result, Err(LuxError::InvalidTransaction(_))));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let transaction = transaction();
+
+        let json = serde_json::to_string(&transaction).unwrap();
+        let decoded: Transaction = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded.id(), transaction.id());
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+    }
+
+    #[test]
+    fn transactions_need_inputs_and_outputs() {
+        let output = TransactionOutput::new(Address::new("bob".to_string()), Luxcoin::new(1));
+        assert!(matches!(
+            Transaction::new(vec![], vec![output.unwrap()], 0),
+            Err(LuxError::InvalidTransaction(_))
+        ));
+        let input = TransactionInput::new(transaction().id, OutputIndex::new(0));
+        assert!(matches!(
+            Transaction::new(vec![input], vec![], 0),
+            Err(LuxError::InvalidTransaction(_))
+        ));
+
+        // Peers can't sneak them in through deserialization either
+        for field in ["inputs", "outputs"] {
+            let mut json = serde_json::to_value(transaction()).unwrap();
+            json[field] = serde_json::json!([]);
+            assert!(serde_json::from_value::<Transaction>(json).is_err());
+        }
+    }
+
+    #[test]
+    fn deserialization_rejects_forged_id() {
+        let mut json = serde_json::to_value(transaction()).unwrap();
+        json["id"] = serde_json::to_value(Sha256::new([0; 32])).unwrap();
+
+        let error = serde_json::from_value::<Transaction>(json).unwrap_err();
+        assert!(error.to_string().starts_with("Transaction id mismatch"));
+    }
+
+    #[test]
+    fn transaction_id_from_str() {
+        let transaction = transaction();
+
+        assert_eq!(
+            transaction
+                .id()
+                .to_string()
+                .parse::<TransactionId>()
+                .unwrap(),
+            *transaction.id()
+        );
+        assert!(matches!(
+            "0x".parse::<TransactionId>(),
+            Err(LuxError::HashParse(_))
+        ));
+    }
+
+    #[test]
+    fn builder_matches_new() {
+        let funding = TransactionId::new(crypto::hash(b"funding"));
+        let alice = Address::new("alice".to_string());
+        let built = TransactionBuilder::new()
+            .add_input(funding, OutputIndex::new(0))
+            .add_output(alice.clone(), Luxcoin::new(50))
+            .unwrap()
+            .locktime(7)
+            .build()
+            .unwrap();
+        let expected = Transaction::new(
+            vec![TransactionInput::new(funding, OutputIndex::new(0))],
+            vec![TransactionOutput::new(alice.clone(), Luxcoin::new(50)).unwrap()],
+            7,
+        )
+        .unwrap();
+        assert_eq!(built.id(), expected.id());
+
+        assert!(matches!(
+            TransactionBuilder::new().add_output(alice.clone(), Luxcoin::ZERO),
+            Err(LuxError::InvalidAmount(_))
+        ));
+        assert!(matches!(
+            TransactionBuilder::new()
+                .add_input(funding, OutputIndex::new(0))
+                .build(),
+            Err(LuxError::InvalidTransaction(_))
+        ));
+        assert!(matches!(
+            TransactionBuilder::new()
+                .add_output(alice, Luxcoin::new(50))
+                .unwrap()
+                .build(),
+            Err(LuxError::InvalidTransaction(_))
+        ));
+    }
+
+    #[test]
+    fn equality_follows_id() {
+        let transaction = transaction();
+        let mut signed = transaction.clone();
+        signed
+            .sign_input(
+                0,
+                &KeyPair::from_secret_bytes(&[7; 32]).unwrap(),
+                &spent_output(50),
+            )
+            .unwrap();
+        let other = Transaction::new(
+            transaction.inputs().clone(),
+            vec![
+                TransactionOutput::new(Address::new("carol".to_string()), Luxcoin::new(1)).unwrap(),
+            ],
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(transaction, signed);
+        assert_ne!(transaction, other);
+        assert_eq!(HashSet::from([transaction.clone(), signed, other]).len(), 2);
+        assert_eq!(transaction.outputs()[0], transaction.outputs()[0].clone());
+        assert_ne!(transaction.outputs()[0], transaction.outputs()[1]);
+    }
+
+    #[test]
+    fn locktime_by_height_or_time() {
+        let locked = |locktime| {
+            Transaction::new(
+                transaction().inputs().clone(),
+                transaction().outputs().clone(),
+                locktime,
+            )
+            .unwrap()
+        };
+        let time = Timestamp::new(1_600_000_000);
+
+        assert!(locked(0).is_final(0, Timestamp::new(0)));
+        assert!(!locked(10).is_final(9, time));
+        assert!(locked(10).is_final(10, time));
+        assert!(!locked(1_600_000_001).is_final(u32::MAX, time));
+        assert!(locked(1_600_000_000).is_final(0, time));
+        assert_ne!(locked(0).id(), locked(10).id());
+    }
+
+    #[test]
+    fn output_index_validity() {
+        assert!(OutputIndex::new(0).is_valid());
+        assert!(OutputIndex::new(-1).is_valid());
+        assert!(!OutputIndex::new(-2).is_valid());
+        assert!(!OutputIndex::new(i32::MIN).is_valid());
+        assert_eq!(OutputIndex::new(3).as_usize(), Some(3));
+        assert_eq!(OutputIndex::new(-1).as_usize(), None);
+
+        let invalid = Transaction::new(
+            vec![TransactionInput::new(
+                TransactionId::new(crypto::hash(b"previous")),
+                OutputIndex::new(-2),
+            )],
+            transaction().outputs().clone(),
+            0,
+        );
+        assert!(matches!(invalid, Err(LuxError::InvalidTransaction(_))));
+    }
+
+    #[test]
+    fn id_is_pinned() {
+        assert_eq!(
+            transaction().id().to_string(),
+            "1eb0b192768f1e3c15ef2c202fbb7a481c3d6dd5f4e49f67090c2891ae1e5448"
+        );
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let transaction = transaction();
+        let result = Transaction::with_version(
+            TRANSACTION_VERSION + 1,
+            transaction.inputs().clone(),
+            transaction.outputs().clone(),
+            transaction.locktime(),
+        );
+
+        assert!(matches!(result, Err(LuxError::InvalidTransaction(_))));
+    }
+
+    #[test]
+    fn missing_version_defaults_to_current() {
+        let mut json = serde_json::to_value(transaction()).unwrap();
+        json.as_object_mut().unwrap().remove("version");
+
+        let decoded = serde_json::from_value::<Transaction>(json).unwrap();
+        assert_eq!(decoded.version(), TRANSACTION_VERSION);
+        assert_eq!(decoded.id(), transaction().id());
+    }
+
+    #[test]
+    fn id_commits_to_locktime() {
+        let transaction = transaction();
+        let locked = Transaction::new(
+            transaction.inputs().clone(),
+            transaction.outputs().clone(),
+            1,
+        )
+        .unwrap();
+
+        assert_ne!(transaction.id(), locked.id());
+    }
+
+    #[test]
+    fn verify_id() {
+        let mut transaction = transaction();
+        assert!(transaction.verify_id());
+
+        transaction.id = TransactionId::new(Sha256::new([0; 32]));
+        assert!(!transaction.verify_id());
+    }
+
+    #[test]
+    fn deserialization_rejects_non_positive_outputs() {
+        let mut json = serde_json::to_value(transaction()).unwrap();
+        json["outputs"][0]["Payment"]["amount"] = serde_json::to_value(0).unwrap();
+
+        assert!(serde_json::from_value::<Transaction>(json).is_err());
+    }
+
+    #[test]
+    fn deserialization_rejects_coinbase_height_on_regular_input() {
+        let mut json = serde_json::to_value(transaction()).unwrap();
+        json["inputs"][0]["coinbase_height"] = serde_json::to_value(1).unwrap();
+
+        assert!(serde_json::from_value::<Transaction>(json).is_err());
+    }
+
+    #[test]
+    fn new_coinbase_pays_subsidy_and_fees() {
+        let miner = Address::new("miner".to_string());
+        let coinbase =
+            Transaction::new_coinbase(miner.clone(), Luxcoin::new(50), Luxcoin::new(7), 3).unwrap();
+
+        assert!(coinbase.is_coinbase());
+        assert_eq!(coinbase.inputs()[0].coinbase_height(), Some(3));
+        assert_eq!(coinbase.outputs().len(), 1);
+        assert_eq!(coinbase.outputs()[0].amount(), Luxcoin::new(57));
+        assert!(coinbase.validate_coinbase_amount(Luxcoin::new(57)).is_ok());
+
+        let next =
+            Transaction::new_coinbase(miner.clone(), Luxcoin::new(50), Luxcoin::new(7), 4).unwrap();
+        assert_ne!(coinbase.id(), next.id());
+
+        assert!(matches!(
+            Transaction::new_coinbase(miner.clone(), Luxcoin::new(i64::MAX), Luxcoin::new(1), 0),
+            Err(LuxError::InvalidTransaction(_))
+        ));
+        assert!(matches!(
+            Transaction::new_coinbase(miner, Luxcoin::new(0), Luxcoin::new(0), 0),
+            Err(LuxError::InvalidAmount(_))
+        ));
+    }
+
+    #[test]
+    fn output_amount_must_be_positive() {
+        let to = Address::new("alice".to_string());
+
+        assert!(TransactionOutput::new(to.clone(), Luxcoin::new(1)).is_ok());
+        assert!(matches!(
+            TransactionOutput::new(to.clone(), Luxcoin::new(0)),
+            Err(LuxError::InvalidAmount(_))
+        ));
+        assert!(matches!(
+            TransactionOutput::new(to, Luxcoin::new(-10)),
+            Err(LuxError::InvalidAmount(_))
+        ));
+    }
+
+    #[test]
+    fn data_outputs_are_bounded_and_committed() {
+        let data = |byte: u8, len: usize| TransactionOutput::new_data(vec![byte; len]);
+        assert!(matches!(
+            data(0, MAX_DATA_OUTPUT_SIZE + 1),
+            Err(LuxError::InvalidTransaction(_))
+        ));
+
+        let output = data(7, MAX_DATA_OUTPUT_SIZE).unwrap();
+        assert_eq!(output.amount(), Luxcoin::ZERO);
+        assert_eq!(output.to(), None);
+        assert_eq!(output.data(), Some(&[7; MAX_DATA_OUTPUT_SIZE][..]));
+        assert!(!output.is_spendable());
+
+        let build = |data: Vec<u8>| {
+            TransactionBuilder::new()
+                .add_input(
+                    TransactionId::new(crypto::hash(b"funding")),
+                    OutputIndex::new(0),
+                )
+                .add_output(Address::new("alice".to_string()), Luxcoin::new(10))
+                .unwrap()
+                .add_data_output(data)
+                .unwrap()
+                .build()
+                .unwrap()
+        };
+        let transaction = build(b"commitment".to_vec());
+        assert_eq!(transaction.total_output(), Luxcoin::new(10));
+        assert_ne!(transaction.id(), build(b"commitments".to_vec()).id());
+
+        let bytes = bincode::serialize(&transaction).unwrap();
+        let decoded = bincode::deserialize::<Transaction>(&bytes).unwrap();
+        assert_eq!(decoded.outputs()[1].data(), Some(&b"commitment"[..]));
+
+        let mut json = serde_json::to_value(&transaction).unwrap();
+        json["outputs"][1]["Data"] =
+            serde_json::to_value(vec![0; MAX_DATA_OUTPUT_SIZE + 1]).unwrap();
+        assert!(serde_json::from_value::<Transaction>(json).is_err());
+    }
+
+    #[test]
+    fn sign_and_verify_input() {
+        let mut transaction = transaction();
+        let id = *transaction.id();
+        let spent = spent_output(50);
+        assert!(!transaction.verify_input(0, &spent));
+
+        transaction
+            .sign_input(0, &KeyPair::generate(), &spent)
+            .unwrap();
+        assert!(transaction.verify_input(0, &spent));
+        assert_eq!(
+            bincode::serialized_size(&transaction.inputs()[0]).unwrap(),
+            SIGNED_INPUT_SIZE as u64
+        );
+        assert_eq!(transaction.id(), &id);
+        assert!(transaction
+            .sign_input(1, &KeyPair::generate(), &spent)
+            .is_err());
+        assert!(!transaction.verify_input(1, &spent));
+
+        let json = serde_json::to_string(&transaction).unwrap();
+        let decoded: Transaction = serde_json::from_str(&json).unwrap();
+        assert!(decoded.verify_input(0, &spent));
+    }
+
+    #[test]
+    fn weight_follows_signing() {
+        let mut transaction = transaction();
+        let unsigned = transaction.weight();
+        assert_eq!(
+            unsigned,
+            transaction.serialized_size() as u64 * WITNESS_SCALE_FACTOR
+        );
+
+        transaction
+            .sign_input(0, &KeyPair::generate(), &spent_output(50))
+            .unwrap();
+        assert!(transaction.weight() > unsigned);
+        assert_eq!(
+            transaction.weight(),
+            transaction.serialized_size() as u64 * WITNESS_SCALE_FACTOR
+        );
+    }
+
+    #[test]
+    fn tampered_input_fails_verification() {
+        let mut transaction = transaction();
+        let spent = spent_output(50);
+        transaction
+            .sign_input(0, &KeyPair::generate(), &spent)
+            .unwrap();
+
+        assert!(!transaction.verify_input(0, &spent_output(60)));
+        transaction.inputs[0].pubkey = Some(KeyPair::generate().public_key());
+        assert!(!transaction.verify_input(0, &spent));
+    }
+
+    #[test]
+    fn inputs_must_be_signed_by_the_owner() {
+        let owner = KeyPair::generate();
+        let spent = TransactionOutput::new(
+            Address::from_pubkey(&owner.public_key(), Network::Mainnet),
+            Luxcoin::new(50),
+        )
+        .unwrap();
+        let mut transaction = transaction();
+        let lookup = |_: &TransactionId, _: &OutputIndex| Some(&spent);
+        assert!(matches!(
+            transaction.verify_inputs_with(lookup),
+            Err(LuxError::InvalidSignature { .. })
+        ));
+
+        // A valid signature, but not by the key the output pays to
+        transaction
+            .sign_input(0, &KeyPair::generate(), &spent)
+            .unwrap();
+        assert!(transaction.verify_input(0, &spent));
+        assert!(matches!(
+            transaction.verify_inputs_with(lookup),
+            Err(LuxError::InvalidSignature { .. })
+        ));
+
+        transaction.sign_input(0, &owner, &spent).unwrap();
+        transaction.verify_inputs_with(lookup).unwrap();
+        assert!(matches!(
+            transaction.verify_inputs_with(|_, _| None),
+            Err(LuxError::MissingOutput { .. })
+        ));
+
+        // Outputs to unhashed addresses can't be spent at all
+        let unowned = spent_output(50);
+        transaction.sign_input(0, &owner, &unowned).unwrap();
+        assert!(matches!(
+            transaction.verify_inputs_with(|_, _| Some(&unowned)),
+            Err(LuxError::InvalidSignature { .. })
+        ));
+    }
+
+    #[test]
+    fn sighash_commits_to_input_and_spent_output() {
+        let transaction = transaction();
+        let spent = spent_output(50);
+        let sighash = transaction.sighash(0, &spent).unwrap();
+
+        assert_eq!(transaction.sighash(0, &spent).unwrap(), sighash);
+        assert_eq!(
+            transaction
+                .sighash_with(0, &spent, SighashType::All)
+                .unwrap(),
+            sighash
         );
-        TransactionId(crypto::hash(data.as_bytes()))
+        assert_ne!(transaction.sighash(0, &spent_output(51)).unwrap(), sighash);
+        assert!(transaction.sighash(1, &spent).is_err());
     }
 }
@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+
+use crate::core::{
+    block::{Block, BlockHash},
+    error::{LuxError, LuxResult},
+    transaction::{OutputIndex, TransactionId, TransactionOutput},
+};
+
+pub type OutPoint = (TransactionId, OutputIndex);
+
+// The set of currently unspent transaction outputs
+#[derive(Default)]
+pub struct UtxoSet {
+    outputs: HashMap<OutPoint, TransactionOutput>,
+    // Outputs spent by each applied block, kept so the block can be reverted
+    spent: HashMap<BlockHash, Vec<(OutPoint, TransactionOutput)>>,
+    // Block that spent each output in `spent`, so that a double spend can be
+    // told apart from a missing output without searching the undo data
+    spent_by: HashMap<OutPoint, BlockHash>,
+}
+
+impl UtxoSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, utxo_id: &TransactionId, index: &OutputIndex) -> Option<&TransactionOutput> {
+        self.outputs.get(&(*utxo_id, *index))
+    }
+
+    pub fn contains(&self, utxo_id: &TransactionId, index: &OutputIndex) -> bool {
+        self.outputs.contains_key(&(*utxo_id, *index))
+    }
+
+    pub fn len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.outputs.is_empty()
+    }
+
+    // Every unspent output, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&OutPoint, &TransactionOutput)> {
+        self.outputs.iter()
+    }
+
+    // Spends every input and adds every output of the block's transactions, in
+    // order. Either the whole block is applied or the set is left untouched.
+    pub fn apply_block(&mut self, block: &Block) -> LuxResult<()> {
+        if self.spent.contains_key(block.id()) {
+            return Err(LuxError::InvalidBlock(format!(
+                "Block: {} has already been applied.",
+                block.id()
+            )));
+        }
+
+        let mut spent = vec![];
+        let mut created = vec![];
+        match self.apply_transactions(block, &mut spent, &mut created) {
+            Ok(()) => {
+                for (outpoint, _) in &spent {
+                    self.spent_by.insert(*outpoint, *block.id());
+                }
+                self.spent.insert(*block.id(), spent);
+                Ok(())
+            }
+            Err(e) => {
+                // Outputs created and spent within the block show up in both
+                self.outputs.extend(spent);
+                created.iter().for_each(|outpoint| {
+                    self.outputs.remove(outpoint);
+                });
+                Err(e)
+            }
+        }
+    }
+
+    fn apply_transactions(
+        &mut self,
+        block: &Block,
+        spent: &mut Vec<(OutPoint, TransactionOutput)>,
+        created: &mut Vec<OutPoint>,
+    ) -> LuxResult<()> {
+        for transaction in block.transactions() {
+            for input in transaction.inputs().iter().filter(|i| !i.is_coinbase()) {
+                let outpoint = (*input.utxo_id(), *input.output_index());
+                match self.outputs.remove(&outpoint) {
+                    Some(output) => spent.push((outpoint, output)),
+                    None if self.was_spent(&outpoint, spent) => {
+                        return Err(LuxError::DoubleSpend {
+                            utxo: outpoint.0,
+                            index: outpoint.1,
+                        })
+                    }
+                    None => {
+                        return Err(LuxError::MissingOutput {
+                            utxo: outpoint.0,
+                            index: outpoint.1,
+                        })
+                    }
+                }
+            }
+
+            for (i, output) in transaction.outputs().iter().enumerate() {
+                if !output.is_spendable() {
+                    continue;
+                }
+                let outpoint = (*transaction.id(), OutputIndex::new(i as i32));
+                if self.outputs.contains_key(&outpoint) {
+                    return Err(LuxError::InvalidTransaction(format!(
+                        "Transaction: {} would overwrite an unspent output.",
+                        transaction.id()
+                    )));
+                }
+
+                self.outputs.insert(outpoint, output.clone());
+                created.push(outpoint);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn was_spent(&self, outpoint: &OutPoint, pending: &[(OutPoint, TransactionOutput)]) -> bool {
+        self.spent_by.contains_key(outpoint) || pending.iter().any(|(spent, _)| spent == outpoint)
+    }
+
+    // Undoes `apply_block`. Blocks must be reverted in the reverse order they
+    // were applied.
+    pub fn revert_block(&mut self, block: &Block) -> LuxResult<()> {
+        let spent = self.spent.get(block.id()).ok_or_else(|| {
+            LuxError::InvalidBlock(format!("Block: {} has not been applied.", block.id()))
+        })?;
+
+        let created = block
+            .iter_outpoints()
+            .filter(|(_, _, output)| output.is_spendable())
+            .map(|(utxo_id, index, _)| (utxo_id, index))
+            .collect::<Vec<OutPoint>>();
+        let spent_later = created.iter().any(|outpoint| {
+            !self.outputs.contains_key(outpoint) && !spent.iter().any(|(o, _)| o == outpoint)
+        });
+        if spent_later {
+            return Err(LuxError::InvalidBlock(format!(
+                "Block: {} has outputs spent by a later block.",
+                block.id()
+            )));
+        }
+
+        let spent = self.spent.remove(block.id()).unwrap_or_default();
+        for (outpoint, _) in &spent {
+            self.spent_by.remove(outpoint);
+        }
+        self.outputs.extend(spent);
+        created.iter().for_each(|outpoint| {
+            self.outputs.remove(outpoint);
+        });
+        Ok(())
+    }
+
+    // Whether the undo data of an applied block is still around
+    pub fn can_revert(&self, hash: &BlockHash) -> bool {
+        self.spent.contains_key(hash)
+    }
+
+    // Drops the undo data of a block that can't be reverted anymore. Spending
+    // its inputs again then fails as a missing output rather than a double
+    // spend.
+    pub fn forget_undo(&mut self, hash: &BlockHash) {
+        for (outpoint, _) in self.spent.remove(hash).unwrap_or_default() {
+            self.spent_by.remove(&outpoint);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UtxoSet;
+    use crate::core::{
+        address::Address,
+        block::{Block, BlockHash, BlockHeader},
+        crypto::{MerkleTree, Sha256},
+        error::LuxError,
+        luxcoin::Luxcoin,
+        timestamp::Timestamp,
+        transaction::{OutputIndex, Transaction, TransactionInput, TransactionOutput},
+    };
+
+    fn output(to: &str, amount: i64) -> TransactionOutput {
+        TransactionOutput::new(Address::new(to.to_string()), Luxcoin::new(amount)).unwrap()
+    }
+
+    fn coinbase(to: &str, amount: i64) -> Transaction {
+        Transaction::new(
+            vec![TransactionInput::new_coinbase(0)],
+            vec![output(to, amount)],
+            0,
+        )
+        .unwrap()
+    }
+
+    fn spend(from: &Transaction, index: i32, outputs: Vec<TransactionOutput>) -> Transaction {
+        Transaction::new(
+            vec![TransactionInput::new(*from.id(), OutputIndex::new(index))],
+            outputs,
+            0,
+        )
+        .unwrap()
+    }
+
+    fn block(previous: &BlockHash, transactions: Vec<Transaction>) -> Block {
+        let merkle_root = MerkleTree::from(&transactions).as_ref().clone();
+        let header = BlockHeader::new(*previous, merkle_root, Timestamp::new(0), 0, 0);
+        Block::new(header, transactions)
+    }
+
+    #[test]
+    fn apply_and_revert() {
+        let mut utxos = UtxoSet::new();
+        let reward = coinbase("alice", 50);
+        let genesis = block(&BlockHash::new(Sha256::new([0; 32])), vec![reward.clone()]);
+        utxos.apply_block(&genesis).unwrap();
+        assert!(utxos.contains(reward.id(), &OutputIndex::new(0)));
+
+        let payment = spend(&reward, 0, vec![output("bob", 20), output("alice", 30)]);
+        let next = block(genesis.id(), vec![coinbase("alice", 51), payment.clone()]);
+        utxos.apply_block(&next).unwrap();
+        assert!(!utxos.contains(reward.id(), &OutputIndex::new(0)));
+        assert_eq!(
+            utxos
+                .get(payment.id(), &OutputIndex::new(0))
+                .unwrap()
+                .amount(),
+            Luxcoin::new(20)
+        );
+        assert_eq!(utxos.len(), 3);
+
+        assert!(utxos.revert_block(&genesis).is_err());
+        utxos.revert_block(&next).unwrap();
+        assert!(utxos.contains(reward.id(), &OutputIndex::new(0)));
+        assert_eq!(utxos.len(), 1);
+
+        utxos.revert_block(&genesis).unwrap();
+        assert!(utxos.is_empty());
+    }
+
+    #[test]
+    fn data_outputs_are_left_out() {
+        let mut utxos = UtxoSet::new();
+        let reward = coinbase("alice", 50);
+        let genesis = block(&BlockHash::new(Sha256::new([0; 32])), vec![reward.clone()]);
+        utxos.apply_block(&genesis).unwrap();
+
+        let data = TransactionOutput::new_data(b"commitment".to_vec()).unwrap();
+        let payment = spend(&reward, 0, vec![output("bob", 50), data]);
+        let next = block(genesis.id(), vec![payment.clone()]);
+        utxos.apply_block(&next).unwrap();
+        assert!(utxos.contains(payment.id(), &OutputIndex::new(0)));
+        assert!(!utxos.contains(payment.id(), &OutputIndex::new(1)));
+        assert_eq!(utxos.len(), 1);
+
+        utxos.revert_block(&next).unwrap();
+        assert!(utxos.contains(reward.id(), &OutputIndex::new(0)));
+        assert_eq!(utxos.len(), 1);
+    }
+
+    #[test]
+    fn outputs_spent_within_the_same_block() {
+        let mut utxos = UtxoSet::new();
+        let reward = coinbase("alice", 50);
+        let genesis = block(&BlockHash::new(Sha256::new([0; 32])), vec![reward.clone()]);
+        utxos.apply_block(&genesis).unwrap();
+
+        let first = spend(&reward, 0, vec![output("bob", 50)]);
+        let second = spend(&first, 0, vec![output("carol", 50)]);
+        let next = block(genesis.id(), vec![first.clone(), second.clone()]);
+        utxos.apply_block(&next).unwrap();
+        assert!(!utxos.contains(first.id(), &OutputIndex::new(0)));
+        assert!(utxos.contains(second.id(), &OutputIndex::new(0)));
+
+        utxos.revert_block(&next).unwrap();
+        assert!(utxos.contains(reward.id(), &OutputIndex::new(0)));
+        assert_eq!(utxos.len(), 1);
+
+        // A failure after an intra-block spend must not resurrect its output
+        let invalid = spend(&reward, 1, vec![output("dave", 50)]);
+        let failing = block(genesis.id(), vec![first.clone(), second, invalid]);
+        assert!(utxos.apply_block(&failing).is_err());
+        assert!(!utxos.contains(first.id(), &OutputIndex::new(0)));
+        assert!(utxos.contains(reward.id(), &OutputIndex::new(0)));
+        assert_eq!(utxos.len(), 1);
+    }
+
+    #[test]
+    fn rejects_double_spends() {
+        let mut utxos = UtxoSet::new();
+        let reward = coinbase("alice", 50);
+        let genesis = block(&BlockHash::new(Sha256::new([0; 32])), vec![reward.clone()]);
+        utxos.apply_block(&genesis).unwrap();
+
+        let first = spend(&reward, 0, vec![output("bob", 50)]);
+        let second = spend(&reward, 0, vec![output("carol", 50)]);
+        let within_block = block(genesis.id(), vec![first.clone(), second.clone()]);
+        assert!(matches!(
+            utxos.apply_block(&within_block),
+            Err(LuxError::DoubleSpend { .. })
+        ));
+        // A failed block leaves the set untouched
+        assert!(utxos.contains(reward.id(), &OutputIndex::new(0)));
+        assert_eq!(utxos.len(), 1);
+
+        let next = block(genesis.id(), vec![first]);
+        utxos.apply_block(&next).unwrap();
+        let later = block(next.id(), vec![second]);
+        assert!(matches!(
+            utxos.apply_block(&later),
+            Err(LuxError::DoubleSpend { .. })
+        ));
+    }
+
+    #[test]
+    fn forget_undo_drops_history() {
+        let mut utxos = UtxoSet::new();
+        let reward = coinbase("alice", 50);
+        let genesis = block(&BlockHash::new(Sha256::new([0; 32])), vec![reward.clone()]);
+        utxos.apply_block(&genesis).unwrap();
+        let next = block(
+            genesis.id(),
+            vec![spend(&reward, 0, vec![output("bob", 50)])],
+        );
+        utxos.apply_block(&next).unwrap();
+        assert!(utxos.can_revert(next.id()));
+
+        utxos.forget_undo(next.id());
+        assert!(!utxos.can_revert(next.id()));
+        assert!(utxos.spent_by.is_empty());
+        assert!(utxos.revert_block(&next).is_err());
+
+        let later = block(
+            next.id(),
+            vec![spend(&reward, 0, vec![output("carol", 50)])],
+        );
+        assert!(matches!(
+            utxos.apply_block(&later),
+            Err(LuxError::MissingOutput { .. })
+        ));
+    }
+
+    #[test]
+    fn validate_amounts_returns_fee() {
+        let mut utxos = UtxoSet::new();
+        let reward = coinbase("alice", 50);
+        let genesis = block(&BlockHash::new(Sha256::new([0; 32])), vec![reward.clone()]);
+        utxos.apply_block(&genesis).unwrap();
+
+        let payment = spend(&reward, 0, vec![output("bob", 20), output("alice", 25)]);
+        assert_eq!(payment.validate_amounts(&utxos).unwrap(), Luxcoin::new(5));
+
+        let exact = spend(&reward, 0, vec![output("bob", 50)]);
+        assert_eq!(exact.validate_amounts(&utxos).unwrap(), Luxcoin::new(0));
+
+        assert_eq!(reward.validate_amounts(&utxos).unwrap(), Luxcoin::new(0));
+        assert!(reward.validate_coinbase_amount(Luxcoin::new(50)).is_ok());
+        assert!(reward.validate_coinbase_amount(Luxcoin::new(49)).is_err());
+    }
+
+    #[test]
+    fn validate_amounts_rejects_value_creation() {
+        let mut utxos = UtxoSet::new();
+        let reward = coinbase("alice", 50);
+        let genesis = block(&BlockHash::new(Sha256::new([0; 32])), vec![reward.clone()]);
+        utxos.apply_block(&genesis).unwrap();
+
+        let inflated = spend(&reward, 0, vec![output("bob", 40), output("alice", 11)]);
+        assert!(matches!(
+            inflated.validate_amounts(&utxos),
+            Err(LuxError::InsufficientFunds { required, available })
+                if required == Luxcoin::new(51) && available == Luxcoin::new(50)
+        ));
+
+        let missing = spend(&reward, 1, vec![output("bob", 1)]);
+        assert!(matches!(
+            missing.validate_amounts(&utxos),
+            Err(LuxError::MissingOutput { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_missing_outputs() {
+        let mut utxos = UtxoSet::new();
+        let reward = coinbase("alice", 50);
+
+        let payment = spend(&reward, 1, vec![output("bob", 50)]);
+        let invalid = block(&BlockHash::new(Sha256::new([0; 32])), vec![payment]);
+        assert!(matches!(
+            utxos.apply_block(&invalid),
+            Err(LuxError::MissingOutput { .. })
+        ));
+        assert!(utxos.is_empty());
+    }
+}
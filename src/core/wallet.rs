@@ -0,0 +1,297 @@
+use crate::core::{
+    address::Address,
+    coin_selection::CoinSelection,
+    error::{LuxError, LuxResult},
+    luxcoin::Luxcoin,
+    transaction::{Transaction, TransactionBuilder, TransactionOutput},
+    utxo::{OutPoint, UtxoSet},
+};
+
+// A set of addresses whose unspent outputs can be spent together. The first
+// address receives the change of every transaction the wallet builds.
+pub struct Wallet {
+    addresses: Vec<Address>,
+}
+
+impl Wallet {
+    pub fn new(change_address: Address) -> Self {
+        Self {
+            addresses: vec![change_address],
+        }
+    }
+
+    pub fn add_address(&mut self, address: Address) {
+        if !self.owns(&address) {
+            self.addresses.push(address);
+        }
+    }
+
+    pub fn addresses(&self) -> &[Address] {
+        &self.addresses
+    }
+
+    pub fn change_address(&self) -> &Address {
+        &self.addresses[0]
+    }
+
+    pub fn owns(&self, address: &Address) -> bool {
+        self.addresses.contains(address)
+    }
+
+    // Unspent outputs paying one of the wallet's addresses, largest first
+    pub fn unspent<'a>(&self, utxos: &'a UtxoSet) -> Vec<(OutPoint, &'a TransactionOutput)> {
+        let mut unspent = utxos
+            .iter()
+            .filter(|(_, output)| output.to().is_some_and(|to| self.owns(to)))
+            .map(|(outpoint, output)| (*outpoint, output))
+            .collect::<Vec<(OutPoint, &TransactionOutput)>>();
+        unspent.sort_by(|(lhs_outpoint, lhs), (rhs_outpoint, rhs)| {
+            rhs.amount()
+                .cmp(&lhs.amount())
+                .then_with(|| lhs_outpoint.0.as_ref().cmp(rhs_outpoint.0.as_ref()))
+                .then_with(|| lhs_outpoint.1.as_usize().cmp(&rhs_outpoint.1.as_usize()))
+        });
+        unspent
+    }
+
+    pub fn balance(&self, utxos: &UtxoSet) -> Luxcoin {
+        self.unspent(utxos)
+            .into_iter()
+            .map(|(_, output)| output.amount())
+            .sum()
+    }
+
+    // Builds an unsigned transaction paying `amount` to `to` and leaving `fee`
+    // to the miner. `selector` picks the inputs covering both, and whatever is
+    // left over goes back to the change address.
+    pub fn build_transaction<S: CoinSelection>(
+        &self,
+        to: Address,
+        amount: Luxcoin,
+        fee: Luxcoin,
+        utxos: &UtxoSet,
+        selector: &S,
+    ) -> LuxResult<Transaction> {
+        let fee = Luxcoin::try_new(fee.as_i64())?;
+        let builder = TransactionBuilder::new().add_output(to, amount)?;
+        let required = amount
+            .checked_add(fee)
+            .ok_or(LuxError::InvalidAmount(amount))?;
+
+        let candidates = self
+            .unspent(utxos)
+            .into_iter()
+            .map(|(outpoint, output)| (outpoint, output.amount()))
+            .collect::<Vec<(OutPoint, Luxcoin)>>();
+        let selected =
+            selector
+                .select(&candidates, required)
+                .ok_or_else(|| LuxError::InsufficientFunds {
+                    required,
+                    available: candidates.iter().map(|(_, amount)| *amount).sum(),
+                })?;
+        let available = candidates
+            .iter()
+            .filter(|(outpoint, _)| selected.contains(outpoint))
+            .map(|(_, amount)| *amount)
+            .sum::<Luxcoin>();
+        // Guards against selectors returning too little or unknown outputs
+        if available < required {
+            return Err(LuxError::InsufficientFunds {
+                required,
+                available,
+            });
+        }
+
+        let mut builder = selected
+            .into_iter()
+            .fold(builder, |builder, (utxo_id, index)| {
+                builder.add_input(utxo_id, index)
+            });
+        let change = available - required;
+        if change.is_positive() {
+            builder = builder.add_output(self.change_address().clone(), change)?;
+        }
+
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Wallet;
+    use crate::core::{
+        address::Address,
+        block::{Block, BlockHash, BlockHeader},
+        coin_selection::{BranchAndBound, LargestFirst},
+        crypto::MerkleTree,
+        error::LuxError,
+        luxcoin::Luxcoin,
+        timestamp::Timestamp,
+        transaction::{OutputIndex, Transaction, TransactionInput, TransactionOutput},
+        utxo::UtxoSet,
+    };
+
+    fn address(name: &str) -> Address {
+        Address::new(name.to_string())
+    }
+
+    // Funds alice with outputs of 10, 30 and 60 and bob with one of 100
+    fn funded() -> (Transaction, UtxoSet) {
+        let reward =
+            Transaction::new_coinbase(address("miner"), Luxcoin::new(200), Luxcoin::new(0), 0)
+                .unwrap();
+        let split = Transaction::new(
+            vec![TransactionInput::new(*reward.id(), OutputIndex::new(0))],
+            [("alice", 10), ("alice", 30), ("alice", 60), ("bob", 100)]
+                .iter()
+                .map(|(to, amount)| {
+                    TransactionOutput::new(address(to), Luxcoin::new(*amount)).unwrap()
+                })
+                .collect(),
+            0,
+        )
+        .unwrap();
+
+        let transactions = vec![reward, split.clone()];
+        let merkle_root = MerkleTree::from(&transactions).as_ref().clone();
+        let header = BlockHeader::new(BlockHash::zero(), merkle_root, Timestamp::new(0), 0, 0);
+        let mut utxos = UtxoSet::new();
+        utxos
+            .apply_block(&Block::new(header, transactions))
+            .unwrap();
+        (split, utxos)
+    }
+
+    #[test]
+    fn balance_counts_owned_outputs() {
+        let (_, utxos) = funded();
+        let mut wallet = Wallet::new(address("alice"));
+        assert_eq!(wallet.balance(&utxos), Luxcoin::new(100));
+
+        wallet.add_address(address("bob"));
+        wallet.add_address(address("bob"));
+        assert_eq!(wallet.addresses().len(), 2);
+        assert_eq!(wallet.balance(&utxos), Luxcoin::new(200));
+        assert_eq!(
+            Wallet::new(address("carol")).balance(&utxos),
+            Luxcoin::new(0)
+        );
+    }
+
+    #[test]
+    fn build_transaction_selects_inputs_and_returns_change() {
+        let (split, utxos) = funded();
+        let wallet = Wallet::new(address("alice"));
+
+        let transaction = wallet
+            .build_transaction(
+                address("carol"),
+                Luxcoin::new(65),
+                Luxcoin::new(2),
+                &utxos,
+                &LargestFirst,
+            )
+            .unwrap();
+        let inputs = transaction
+            .inputs()
+            .iter()
+            .map(|input| (*input.utxo_id(), *input.output_index()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            inputs,
+            vec![
+                (*split.id(), OutputIndex::new(2)),
+                (*split.id(), OutputIndex::new(1)),
+            ]
+        );
+        assert_eq!(transaction.outputs()[0].to(), Some(&address("carol")));
+        assert_eq!(transaction.outputs()[0].amount(), Luxcoin::new(65));
+        assert_eq!(transaction.outputs()[1].to(), Some(&address("alice")));
+        assert_eq!(transaction.outputs()[1].amount(), Luxcoin::new(23));
+        assert_eq!(
+            transaction.validate_amounts(&utxos).unwrap(),
+            Luxcoin::new(2)
+        );
+        assert!(transaction.inputs()[0].signature().is_none());
+    }
+
+    #[test]
+    fn build_transaction_without_change() {
+        let (_, utxos) = funded();
+        let wallet = Wallet::new(address("alice"));
+
+        let transaction = wallet
+            .build_transaction(
+                address("carol"),
+                Luxcoin::new(99),
+                Luxcoin::new(1),
+                &utxos,
+                &LargestFirst,
+            )
+            .unwrap();
+        assert_eq!(transaction.inputs().len(), 3);
+        assert_eq!(transaction.outputs().len(), 1);
+    }
+
+    #[test]
+    fn build_transaction_with_exact_match_has_no_change() {
+        let (split, utxos) = funded();
+        let wallet = Wallet::new(address("alice"));
+
+        let transaction = wallet
+            .build_transaction(
+                address("carol"),
+                Luxcoin::new(38),
+                Luxcoin::new(2),
+                &utxos,
+                &BranchAndBound::default(),
+            )
+            .unwrap();
+        let inputs = transaction
+            .inputs()
+            .iter()
+            .map(|input| (*input.utxo_id(), *input.output_index()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            inputs,
+            vec![
+                (*split.id(), OutputIndex::new(1)),
+                (*split.id(), OutputIndex::new(0)),
+            ]
+        );
+        assert_eq!(transaction.outputs().len(), 1);
+    }
+
+    #[test]
+    fn build_transaction_rejects_insufficient_funds() {
+        let (_, utxos) = funded();
+        let wallet = Wallet::new(address("alice"));
+
+        assert!(matches!(
+            wallet.build_transaction(address("carol"), Luxcoin::new(100), Luxcoin::new(1), &utxos, &LargestFirst),
+            Err(LuxError::InsufficientFunds { required, available })
+                if required == Luxcoin::new(101) && available == Luxcoin::new(100)
+        ));
+        assert!(matches!(
+            wallet.build_transaction(
+                address("carol"),
+                Luxcoin::new(10),
+                Luxcoin::new(-1),
+                &utxos,
+                &LargestFirst
+            ),
+            Err(LuxError::InvalidAmount(_))
+        ));
+        assert!(matches!(
+            wallet.build_transaction(
+                address("carol"),
+                Luxcoin::new(0),
+                Luxcoin::new(1),
+                &utxos,
+                &LargestFirst
+            ),
+            Err(LuxError::InvalidAmount(_))
+        ));
+    }
+}
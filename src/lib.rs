@@ -1,3 +1,6 @@
 #![allow(dead_code)]
 
 mod core;
+
+#[cfg(feature = "fuzzing")]
+pub use crate::core::fuzz;